@@ -0,0 +1,368 @@
+//! Source formatter behind `format_dol`
+//!
+//! Works on the scanned source rather than the converted AST, which drops
+//! comments and function bodies. Only whitespace and brace placement
+//! change: indentation, blank lines, trailing spaces, field alignment and
+//! `{` on the header line. Comments, strings and docs bodies are left as
+//! written.
+
+use crate::scan::{scan, LexKind, Lexeme};
+
+/// One level of indentation
+const INDENT: &str = "    ";
+
+/// A formatted output line
+struct Line {
+    text: String,
+    /// Set for `has name: Type` lines so runs of them can be aligned
+    field: Option<FieldLine>,
+}
+
+/// A `has name: Type` line split around its colon
+struct FieldLine {
+    indent: usize,
+    /// `has name:`
+    head: String,
+    /// Everything after the colon
+    rest: String,
+}
+
+impl Line {
+    fn blank() -> Self {
+        Line {
+            text: String::new(),
+            field: None,
+        }
+    }
+}
+
+pub(crate) fn is_opener(lexeme: &Lexeme) -> bool {
+    lexeme.is_punct('{') || lexeme.is_punct('(') || lexeme.is_punct('[')
+}
+
+pub(crate) fn is_closer(lexeme: &Lexeme) -> bool {
+    lexeme.is_punct('}') || lexeme.is_punct(')') || lexeme.is_punct(']')
+}
+
+/// Split a `has name: Type` line, given the lexemes starting on it
+fn field_line(content: &str, content_offset: usize, lexemes: &[&Lexeme]) -> Option<FieldLine> {
+    match lexemes {
+        [has, name, colon, _, ..]
+            if has.kind == LexKind::Word
+                && has.text == "has"
+                && name.kind == LexKind::Word
+                && colon.is_punct(':') =>
+        {
+            let rest = content[colon.offset - content_offset + 1..].trim_start();
+            Some(FieldLine {
+                indent: 0,
+                head: format!("has {}:", name.text),
+                rest: rest.to_string(),
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Pad runs of consecutive field lines so their types line up
+fn align_fields(lines: &mut [Line]) {
+    let mut start = 0;
+    while start < lines.len() {
+        let Some(indent) = lines[start].field.as_ref().map(|f| f.indent) else {
+            start += 1;
+            continue;
+        };
+        let end = start
+            + lines[start..]
+                .iter()
+                .take_while(|line| line.field.as_ref().is_some_and(|f| f.indent == indent))
+                .count();
+
+        let width = lines[start..end]
+            .iter()
+            .filter_map(|line| line.field.as_ref())
+            .map(|f| f.head.len())
+            .max()
+            .unwrap_or_default();
+        for line in &mut lines[start..end] {
+            if let Some(field) = &line.field {
+                line.text = format!(
+                    "{}{:<width$} {}",
+                    INDENT.repeat(field.indent),
+                    field.head,
+                    field.rest,
+                    width = width
+                );
+            }
+        }
+        start = end;
+    }
+}
+
+/// Reformat DOL source with 4-space indentation
+///
+/// Expects source that parses; callers should fall back to the original
+/// text otherwise. Lines that begin inside a multi-line comment, string or
+/// docs body are kept verbatim.
+pub(crate) fn format_source(source: &str) -> String {
+    let lexemes = scan(source);
+    let raw_lines: Vec<&str> = source.split('\n').collect();
+
+    let mut line_starts = Vec::with_capacity(raw_lines.len());
+    let mut offset = 0;
+    for raw in &raw_lines {
+        line_starts.push(offset);
+        offset += raw.len() + 1;
+    }
+
+    let mut verbatim = vec![false; raw_lines.len()];
+    let mut ends_in_string = vec![false; raw_lines.len()];
+    let mut by_line: Vec<Vec<&Lexeme>> = vec![Vec::new(); raw_lines.len()];
+    for lexeme in &lexemes {
+        let index = lexeme.line - 1;
+        // A docs body that starts its own line keeps that line as written too
+        let first = if lexeme.kind == LexKind::Doc && by_line[index].is_empty() {
+            lexeme.line
+        } else {
+            lexeme.line + 1
+        };
+        for line in first..=lexeme.end_line() {
+            verbatim[line - 1] = true;
+        }
+        if lexeme.kind == LexKind::Str {
+            for line in lexeme.line..lexeme.end_line() {
+                ends_in_string[line - 1] = true;
+            }
+        }
+        by_line[index].push(lexeme);
+    }
+
+    let mut out: Vec<Line> = Vec::new();
+    let mut depth = 0usize;
+    // Whether the previous code line closed a top-level block
+    let mut closed_block = false;
+    // Whether a lone `{` may be pulled up onto the previous output line
+    let mut joinable = false;
+
+    for (i, raw) in raw_lines.iter().enumerate() {
+        let line_lexemes = &by_line[i];
+        let raw = if ends_in_string[i] {
+            raw
+        } else {
+            raw.trim_end()
+        };
+        let content = raw.trim_start();
+        let prev_has_text = out.last().is_some_and(|line| !line.text.is_empty());
+
+        if verbatim[i] {
+            out.push(Line {
+                text: raw.to_string(),
+                field: None,
+            });
+            joinable = false;
+        } else if content.is_empty() {
+            // Collapse runs of blank lines and drop leading ones
+            if prev_has_text {
+                out.push(Line::blank());
+            }
+            joinable = false;
+        } else if content == "{" && joinable {
+            if let Some(prev) = out.last_mut() {
+                prev.text.push_str(" {");
+                prev.field = None;
+            }
+        } else {
+            let leading_closers = line_lexemes.iter().take_while(|l| is_closer(l)).count();
+            let indent = depth.saturating_sub(leading_closers);
+            if indent == 0 && closed_block && prev_has_text {
+                out.push(Line::blank());
+            }
+
+            let content_offset = line_starts[i] + (raw.len() - content.len());
+            let field = field_line(content, content_offset, line_lexemes)
+                .map(|field| FieldLine { indent, ..field });
+            out.push(Line {
+                text: format!("{}{}", INDENT.repeat(indent), content),
+                field,
+            });
+            joinable = line_lexemes
+                .last()
+                .is_some_and(|l| l.kind != LexKind::LineComment);
+        }
+
+        let opens = line_lexemes.iter().filter(|l| is_opener(l)).count();
+        let closes = line_lexemes.iter().filter(|l| is_closer(l)).count();
+        depth = (depth + opens).saturating_sub(closes);
+        if !line_lexemes.is_empty() {
+            closed_block = depth == 0
+                && line_lexemes
+                    .iter()
+                    .rev()
+                    .find(|l| !l.is_comment())
+                    .is_some_and(|l| l.is_punct('}'));
+        }
+    }
+
+    align_fields(&mut out);
+    while out.last().is_some_and(|line| line.text.is_empty()) {
+        out.pop();
+    }
+
+    let mut formatted = out
+        .into_iter()
+        .map(|line| line.text)
+        .collect::<Vec<_>>()
+        .join("\n");
+    if !formatted.is_empty() {
+        formatted.push('\n');
+    }
+    formatted
+}
+
+/// Convert an identifier to `snake_case`, splitting acronyms (`HTTPServer` -> `http_server`)
+pub(crate) fn to_snake_case(name: &str) -> String {
+    let chars: Vec<char> = name.chars().collect();
+    let mut out = String::with_capacity(name.len() + 4);
+    for (i, &c) in chars.iter().enumerate() {
+        if c.is_uppercase() && i > 0 {
+            let prev = chars[i - 1];
+            let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+            if prev != '_'
+                && (prev.is_lowercase()
+                    || prev.is_ascii_digit()
+                    || (next_lower && prev.is_uppercase()))
+            {
+                out.push('_');
+            }
+        }
+        out.extend(c.to_lowercase());
+    }
+    out
+}
+
+/// Convert an identifier to `PascalCase`, dropping underscores
+pub(crate) fn to_pascal_case(name: &str) -> String {
+    name.split('_')
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            chars
+                .next()
+                .map(|first| first.to_uppercase().chain(chars).collect::<String>())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Replace every identifier listed in `renames`, leaving comments, strings
+/// and docs bodies untouched
+pub(crate) fn rename_identifiers(source: &str, renames: &[(String, String)]) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut copied = 0;
+    for lexeme in scan(source) {
+        if lexeme.kind != LexKind::Word {
+            continue;
+        }
+        if let Some((_, to)) = renames.iter().find(|(from, _)| from == lexeme.text) {
+            out.push_str(&source[copied..lexeme.offset]);
+            out.push_str(to);
+            copied = lexeme.offset + lexeme.text.len();
+        }
+    }
+    out.push_str(&source[copied..]);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(lines: &[&str]) -> String {
+        lines.join("\n")
+    }
+
+    #[test]
+    fn test_format_messy_source() {
+        let messy = lines(&[
+            "",
+            "",
+            "gen Counter",
+            "{",
+            "  has x:   i32   ",
+            "        has   count: i64",
+            "  counter is tracked // note",
+            "}",
+            "docs {",
+            "    A counter.",
+            "      Indented prose stays.",
+            "}",
+            "",
+            "",
+            "",
+            "fun add(a: i64, b: i64) -> i64 {",
+            "return a + b",
+            "}",
+        ]);
+        let expected = lines(&[
+            "gen Counter {",
+            "    has x:     i32",
+            "    has count: i64",
+            "    counter is tracked // note",
+            "}",
+            "",
+            "docs {",
+            "    A counter.",
+            "      Indented prose stays.",
+            "}",
+            "",
+            "fun add(a: i64, b: i64) -> i64 {",
+            "    return a + b",
+            "}",
+            "",
+        ]);
+        assert_eq!(format_source(&messy), expected);
+        assert_eq!(format_source(&expected), expected);
+    }
+
+    #[test]
+    fn test_case_conversion() {
+        assert_eq!(to_snake_case("addItem"), "add_item");
+        assert_eq!(to_snake_case("HTTPServer"), "http_server");
+        assert_eq!(to_snake_case("already_snake"), "already_snake");
+        assert_eq!(to_snake_case("item2Count"), "item2_count");
+        assert_eq!(to_pascal_case("counter_state"), "CounterState");
+        assert_eq!(to_pascal_case("counterState"), "CounterState");
+    }
+
+    #[test]
+    fn test_rename_identifiers_skips_strings_and_comments() {
+        let renames = vec![("addItem".to_string(), "add_item".to_string())];
+        let source = "fun addItem() {} // addItem\nconst s = \"addItem\"\naddItem()";
+        assert_eq!(
+            rename_identifiers(source, &renames),
+            "fun add_item() {} // addItem\nconst s = \"addItem\"\nadd_item()"
+        );
+    }
+
+    #[test]
+    fn test_format_keeps_comments_and_strings() {
+        let source = lines(&[
+            "/* header",
+            "   keeps   its spacing */",
+            "gen A {",
+            "  has name: String = \"two",
+            "  lines\"",
+            "}",
+        ]);
+        let expected = lines(&[
+            "/* header",
+            "   keeps   its spacing */",
+            "gen A {",
+            "    has name: String = \"two",
+            "  lines\"",
+            "}",
+            "",
+        ]);
+        assert_eq!(format_source(&source), expected);
+    }
+}
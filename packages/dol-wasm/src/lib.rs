@@ -13,14 +13,19 @@
 //! maintaining a separate parser implementation.
 
 use metadol::{
-    ast::{Declaration, DolFile, Quantifier, Statement, Visibility},
-    parse_and_validate, parse_file, parse_file_all,
+    ast::{Declaration, DolFile, Purity, Quantifier, Statement, TypeExpr, Visibility},
+    parse_and_validate, parse_file_all,
     wasm::WasmCompiler,
     ParseError,
 };
+use scan::{scan, LexKind, Lexeme};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use wasm_bindgen::prelude::*;
 
+mod format;
+mod scan;
+
 /// Initialize panic hook for better error messages in browser console
 #[wasm_bindgen(start)]
 pub fn init() {
@@ -29,7 +34,7 @@ pub fn init() {
 }
 
 /// Compilation result returned to JavaScript
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CompileResult {
     /// Whether compilation was successful
     pub success: bool,
@@ -37,14 +42,32 @@ pub struct CompileResult {
     pub ast: Vec<AstNode>,
     /// Any errors encountered
     pub errors: Vec<CompileError>,
-    /// Any warnings
+    /// Any warnings, each rendered as `Kind: message`
     pub warnings: Vec<String>,
     /// Metadata about the compilation
     pub metadata: CompileMetadata,
 }
 
+/// Result of `parse_dol`: a `CompileResult` without metadata
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ParseResult {
+    /// Whether parsing was successful
+    pub success: bool,
+    /// Parsed AST (serialized declarations)
+    pub ast: Vec<AstNode>,
+    /// Any errors encountered
+    pub errors: Vec<CompileError>,
+    /// Always empty; the checks that produce warnings run in `compile_dol`
+    pub warnings: Vec<String>,
+}
+
 /// Simplified AST node for browser consumption
-#[derive(Debug, Clone, Serialize, Deserialize)]
+///
+/// Declarations returned by the compile and parse exports carry a
+/// `checksum` of their source tokens (see `assign_checksums`), so callers
+/// caching per-declaration output can tell which declarations changed
+/// between compiles.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum AstNode {
     /// A gene declaration
@@ -55,6 +78,8 @@ pub enum AstNode {
         statements: Vec<StatementNode>,
         exegesis: String,
         line: usize,
+        #[serde(default)]
+        checksum: String,
     },
     /// A trait declaration
     Trait {
@@ -63,6 +88,8 @@ pub enum AstNode {
         statements: Vec<StatementNode>,
         exegesis: String,
         line: usize,
+        #[serde(default)]
+        checksum: String,
     },
     /// A constraint declaration
     Constraint {
@@ -71,6 +98,8 @@ pub enum AstNode {
         statements: Vec<StatementNode>,
         exegesis: String,
         line: usize,
+        #[serde(default)]
+        checksum: String,
     },
     /// A system declaration
     System {
@@ -81,6 +110,8 @@ pub enum AstNode {
         statements: Vec<StatementNode>,
         exegesis: String,
         line: usize,
+        #[serde(default)]
+        checksum: String,
     },
     /// An evolution declaration
     Evolution {
@@ -93,6 +124,8 @@ pub enum AstNode {
         rationale: Option<String>,
         exegesis: String,
         line: usize,
+        #[serde(default)]
+        checksum: String,
     },
     /// A function declaration
     Function {
@@ -102,6 +135,8 @@ pub enum AstNode {
         params: Vec<ParamNode>,
         return_type: Option<String>,
         line: usize,
+        #[serde(default)]
+        checksum: String,
     },
     /// A constant declaration
     Const {
@@ -109,11 +144,13 @@ pub enum AstNode {
         visibility: String,
         const_type: Option<String>,
         line: usize,
+        #[serde(default)]
+        checksum: String,
     },
 }
 
 /// Statement node for browser consumption
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(tag = "kind")]
 pub enum StatementNode {
     /// Property possession: subject has property
@@ -169,15 +206,24 @@ pub enum StatementNode {
     /// Nested function
     Function {
         name: String,
+        #[serde(default)]
+        visibility: String,
+        /// `pure` or `sex`
+        #[serde(default)]
+        purity: String,
     },
     /// Other statement types
     Other {
         description: String,
     },
+    /// Placeholder for statements cut by `CompileOptions::max_output_depth`
+    Truncated {
+        omitted: usize,
+    },
 }
 
 /// Requirement node for system dependencies
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RequirementNode {
     pub name: String,
     pub constraint: String,
@@ -185,33 +231,204 @@ pub struct RequirementNode {
 }
 
 /// Function parameter node
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ParamNode {
     pub name: String,
     pub param_type: String,
 }
 
 /// Compilation error information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CompileError {
     pub message: String,
     pub line: usize,
     pub column: usize,
+    /// Line of the end of the offending span
+    pub end_line: usize,
+    /// Column just past the offending span; `column + 1` when only a point is known
+    pub end_column: usize,
     pub error_type: String,
 }
 
 /// Metadata about the compilation
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct CompileMetadata {
     pub version: String,
     pub gene_count: usize,
     pub trait_count: usize,
     pub constraint_count: usize,
     pub system_count: usize,
+    /// Functions and constants, including functions nested in declarations
     pub function_count: usize,
+    /// Typed `has name: Type` fields across all declarations
+    pub field_count: usize,
     pub source_lines: usize,
 }
 
+/// Traversal over converted AST nodes for native Rust consumers
+///
+/// Every method has a default, and the defaults for block declarations
+/// descend into their statements, so an implementation only overrides the
+/// nodes it cares about. Call `walk` to visit a whole declaration list.
+/// Nested functions are statements rather than declarations, so they get
+/// their own `visit_nested_function` hook instead of `visit_function`.
+pub trait AstVisitor {
+    /// Visit every declaration in order, recursing into bodies
+    fn walk(&mut self, nodes: &[AstNode]) {
+        for node in nodes {
+            self.visit_node(node);
+        }
+    }
+
+    /// Dispatch a declaration to its kind-specific method
+    fn visit_node(&mut self, node: &AstNode) {
+        match node {
+            AstNode::Gene { .. } => self.visit_gene(node),
+            AstNode::Trait { .. } => self.visit_trait(node),
+            AstNode::Constraint { .. } => self.visit_constraint(node),
+            AstNode::System { .. } => self.visit_system(node),
+            AstNode::Evolution { .. } => self.visit_evolution(node),
+            AstNode::Function { .. } => self.visit_function(node),
+            AstNode::Const { .. } => self.visit_const(node),
+        }
+    }
+
+    fn visit_gene(&mut self, node: &AstNode) {
+        self.walk_statements(node);
+    }
+
+    fn visit_trait(&mut self, node: &AstNode) {
+        self.walk_statements(node);
+    }
+
+    fn visit_constraint(&mut self, node: &AstNode) {
+        self.walk_statements(node);
+    }
+
+    fn visit_system(&mut self, node: &AstNode) {
+        self.walk_statements(node);
+    }
+
+    /// Defaults to visiting both added and deprecated statements
+    fn visit_evolution(&mut self, node: &AstNode) {
+        self.walk_statements(node);
+    }
+
+    fn visit_function(&mut self, _node: &AstNode) {}
+
+    fn visit_const(&mut self, _node: &AstNode) {}
+
+    /// Dispatch a statement; fields and nested functions get their own hooks
+    fn visit_statement(&mut self, statement: &StatementNode) {
+        match statement {
+            StatementNode::HasField {
+                name, field_type, ..
+            } => self.visit_field(name, field_type),
+            StatementNode::Function { name, purity, .. } => {
+                self.visit_nested_function(name, purity)
+            }
+            _ => {}
+        }
+    }
+
+    fn visit_field(&mut self, _name: &str, _field_type: &str) {}
+
+    /// `purity` is `pure` or `sex`, as on `AstNode::Function`
+    fn visit_nested_function(&mut self, _name: &str, _purity: &str) {}
+
+    /// Visit each statement in a declaration's body
+    fn walk_statements(&mut self, node: &AstNode) {
+        let statements: Vec<&StatementNode> = match node {
+            AstNode::Gene { statements, .. }
+            | AstNode::Trait { statements, .. }
+            | AstNode::Constraint { statements, .. }
+            | AstNode::System { statements, .. } => statements.iter().collect(),
+            AstNode::Evolution {
+                additions,
+                deprecations,
+                ..
+            } => additions.iter().chain(deprecations).collect(),
+            AstNode::Function { .. } | AstNode::Const { .. } => vec![],
+        };
+        for statement in statements {
+            self.visit_statement(statement);
+        }
+    }
+}
+
+/// Opt-in checks for `compile_dol_with_options`
+///
+/// Every field defaults to off, so an empty options object compiles
+/// exactly like `compile_dol`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CompileOptions {
+    /// Warn when a function declares more than this many parameters
+    pub max_params: Option<usize>,
+    /// Warn when a function body spans more than this many lines, braces included
+    pub max_function_lines: Option<usize>,
+    /// Warn on `/* */` comments that fit on one line and could be `//`
+    pub prefer_line_comments: bool,
+    /// Warn on public declarations without a docs block
+    pub require_docs: bool,
+    /// Warn on non-effectful system methods that read fields but never write one
+    pub read_only_methods: bool,
+    /// Exempt `pub` methods from `read_only_methods`, treating them as getters
+    pub exempt_pub_getters: bool,
+    /// Warn on functions whose body repeats an earlier function's token for token
+    pub duplicate_bodies: bool,
+    /// Warn on number literals in function bodies other than 0, 1 and the allowlist
+    pub warn_magic_numbers: bool,
+    /// Extra literals `warn_magic_numbers` accepts, compared by value
+    pub magic_number_allowlist: Vec<String>,
+    /// Warn on constants declared without a type annotation
+    pub require_types: bool,
+    /// Fail compilation when any warning is reported
+    pub deny_warnings: bool,
+    /// Also report each warning as a `StrictWarning` error on its line
+    pub warnings_as_errors: bool,
+    /// Cut the serialized AST below this depth (declarations are depth 1)
+    pub max_output_depth: Option<usize>,
+}
+
+/// Presets for `CompileOptions`, from the plain compile to failing on warnings
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StrictnessLevel {
+    /// Same as `compile_dol`: no opt-in lints
+    Lenient,
+    /// The boolean lints on; thresholds like `max_params` stay unset
+    Standard,
+    /// `Standard` plus `require_types`, with every warning also an error
+    Strict,
+}
+
+impl CompileOptions {
+    /// Options for a strictness preset
+    ///
+    /// System versions are required by the grammar and malformed ones are
+    /// errors at every level, so no preset needs a flag for them.
+    pub fn from_level(level: StrictnessLevel) -> Self {
+        let standard = || CompileOptions {
+            prefer_line_comments: true,
+            require_docs: true,
+            read_only_methods: true,
+            exempt_pub_getters: true,
+            duplicate_bodies: true,
+            warn_magic_numbers: true,
+            ..CompileOptions::default()
+        };
+        match level {
+            StrictnessLevel::Lenient => CompileOptions::default(),
+            StrictnessLevel::Standard => standard(),
+            StrictnessLevel::Strict => CompileOptions {
+                require_types: true,
+                warnings_as_errors: true,
+                ..standard()
+            },
+        }
+    }
+}
+
 /// Convert visibility to string
 fn visibility_to_string(vis: &Visibility) -> String {
     match vis {
@@ -222,6 +439,31 @@ fn visibility_to_string(vis: &Visibility) -> String {
     }
 }
 
+/// Convert purity to string
+fn purity_to_string(purity: &Purity) -> String {
+    match purity {
+        Purity::Pure => "pure".to_string(),
+        Purity::Sex => "sex".to_string(),
+    }
+}
+
+/// Render a type annotation as it is written in DOL source
+///
+/// Kinds with no written form here fall back to their `Debug` output.
+fn type_to_dol(ty: &TypeExpr) -> String {
+    let list = |types: &[TypeExpr]| types.iter().map(type_to_dol).collect::<Vec<_>>().join(", ");
+    match ty {
+        TypeExpr::Named(name) => name.clone(),
+        TypeExpr::Generic { name, args } => format!("{}<{}>", name, list(args)),
+        TypeExpr::Function {
+            params,
+            return_type,
+        } => format!("({}) -> {}", list(params), type_to_dol(return_type)),
+        TypeExpr::Tuple(types) => format!("({})", list(types)),
+        other => format!("{:?}", other),
+    }
+}
+
 /// Convert metadol Statement to browser-friendly StatementNode
 fn convert_statement(stmt: &Statement) -> StatementNode {
     match stmt {
@@ -235,7 +477,7 @@ fn convert_statement(stmt: &Statement) -> StatementNode {
         },
         Statement::HasField(field) => StatementNode::HasField {
             name: field.name.clone(),
-            field_type: format!("{:?}", field.type_),
+            field_type: type_to_dol(&field.type_),
             default_value: field.default.as_ref().map(|v| format!("{:?}", v)),
         },
         Statement::Is {
@@ -290,6 +532,8 @@ fn convert_statement(stmt: &Statement) -> StatementNode {
         },
         Statement::Function(func) => StatementNode::Function {
             name: func.name.clone(),
+            visibility: visibility_to_string(&func.visibility),
+            purity: purity_to_string(&func.purity),
         },
     }
 }
@@ -304,6 +548,7 @@ fn convert_declaration(decl: &Declaration) -> AstNode {
             statements: gene.statements.iter().map(convert_statement).collect(),
             exegesis: gene.exegesis.clone(),
             line: gene.span.line,
+            checksum: String::new(),
         },
         Declaration::Trait(trait_decl) => AstNode::Trait {
             name: trait_decl.name.clone(),
@@ -315,6 +560,7 @@ fn convert_declaration(decl: &Declaration) -> AstNode {
                 .collect(),
             exegesis: trait_decl.exegesis.clone(),
             line: trait_decl.span.line,
+            checksum: String::new(),
         },
         Declaration::Constraint(constraint) => AstNode::Constraint {
             name: constraint.name.clone(),
@@ -326,6 +572,7 @@ fn convert_declaration(decl: &Declaration) -> AstNode {
                 .collect(),
             exegesis: constraint.exegesis.clone(),
             line: constraint.span.line,
+            checksum: String::new(),
         },
         Declaration::System(system) => AstNode::System {
             name: system.name.clone(),
@@ -343,6 +590,7 @@ fn convert_declaration(decl: &Declaration) -> AstNode {
             statements: system.statements.iter().map(convert_statement).collect(),
             exegesis: system.exegesis.clone(),
             line: system.span.line,
+            checksum: String::new(),
         },
         Declaration::Evolution(evolution) => AstNode::Evolution {
             name: evolution.name.clone(),
@@ -358,330 +606,3301 @@ fn convert_declaration(decl: &Declaration) -> AstNode {
             rationale: evolution.rationale.clone(),
             exegesis: evolution.exegesis.clone(),
             line: evolution.span.line,
+            checksum: String::new(),
         },
         Declaration::Function(func) => AstNode::Function {
             name: func.name.clone(),
             visibility: visibility_to_string(&func.visibility),
-            purity: match func.purity {
-                metadol::ast::Purity::Pure => "pure".to_string(),
-                metadol::ast::Purity::Sex => "sex".to_string(),
-            },
+            purity: purity_to_string(&func.purity),
             params: func
                 .params
                 .iter()
                 .map(|p| ParamNode {
                     name: p.name.clone(),
-                    param_type: format!("{:?}", p.type_ann),
+                    param_type: type_to_dol(&p.type_ann),
                 })
                 .collect(),
-            return_type: func.return_type.as_ref().map(|t| format!("{:?}", t)),
+            return_type: func.return_type.as_ref().map(type_to_dol),
             line: func.span.line,
+            checksum: String::new(),
         },
         Declaration::Const(const_decl) => AstNode::Const {
             name: const_decl.name.clone(),
             visibility: visibility_to_string(&const_decl.visibility),
-            const_type: const_decl.type_ann.as_ref().map(|t| format!("{:?}", t)),
+            const_type: const_decl.type_ann.as_ref().map(type_to_dol),
             line: const_decl.span.line,
+            checksum: String::new(),
         },
         Declaration::SexVar(var) => AstNode::Const {
             name: var.name.clone(),
             visibility: "private".to_string(),
-            const_type: var.type_ann.as_ref().map(|t| format!("{:?}", t)),
+            const_type: var.type_ann.as_ref().map(type_to_dol),
             line: var.span.line,
+            checksum: String::new(),
         },
     }
 }
 
+/// End of the token starting at `line`/`column`, or one character past it
+fn error_end(source: &str, line: usize, column: usize) -> (usize, usize) {
+    scan(source)
+        .iter()
+        .find(|lexeme| lexeme.line == line && lexeme.column == column)
+        .map_or((line, column + 1), |lexeme| {
+            (lexeme.end_line(), lexeme.end_column())
+        })
+}
+
 /// Convert ParseError to CompileError
-fn convert_parse_error(err: &ParseError) -> CompileError {
+fn convert_parse_error(source: &str, err: &ParseError) -> CompileError {
     let span = err.span();
+    let (end_line, end_column) = error_end(source, span.line, span.column);
     CompileError {
         message: err.to_string(),
         line: span.line,
         column: span.column,
+        end_line,
+        end_column,
         error_type: "ParseError".to_string(),
     }
 }
 
-/// Compile DOL source code to an AST
+/// A warning before it is rendered into `CompileResult::warnings`
 ///
-/// This is the main entry point for the WASM module.
-/// It parses the DOL source using metadol and returns a compilation result.
-#[wasm_bindgen]
-pub fn compile_dol(source: &str) -> Result<JsValue, JsValue> {
-    let source_lines = source.lines().count();
+/// Rendered as `Kind: message` so hosts can filter warnings by kind; the
+/// message itself still says where it points.
+#[derive(Debug, Clone, PartialEq)]
+struct Warning {
+    kind: &'static str,
+    /// 1-based line the warning points at
+    line: usize,
+    message: String,
+}
 
-    // Parse all declarations from the source
-    match parse_file_all(source) {
-        Ok(declarations) => {
-            // Convert to browser-friendly format
-            let ast: Vec<AstNode> = declarations.iter().map(convert_declaration).collect();
-
-            // Count declaration types
-            let mut gene_count = 0;
-            let mut trait_count = 0;
-            let mut constraint_count = 0;
-            let mut system_count = 0;
-            let mut function_count = 0;
-
-            for node in &ast {
-                match node {
-                    AstNode::Gene { .. } => gene_count += 1,
-                    AstNode::Trait { .. } => trait_count += 1,
-                    AstNode::Constraint { .. } => constraint_count += 1,
-                    AstNode::System { .. } => system_count += 1,
-                    AstNode::Function { .. } | AstNode::Const { .. } => function_count += 1,
-                    AstNode::Evolution { .. } => {}
-                }
-            }
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.kind, self.message)
+    }
+}
 
-            let result = CompileResult {
-                success: true,
-                ast,
-                errors: vec![],
-                warnings: vec![],
-                metadata: CompileMetadata {
-                    version: env!("CARGO_PKG_VERSION").to_string(),
-                    gene_count,
-                    trait_count,
-                    constraint_count,
-                    system_count,
-                    function_count,
-                    source_lines,
-                },
-            };
+/// Primitive type names built into DOL
+const BUILTIN_TYPES: &[&str] = &[
+    "i8", "i16", "i32", "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize", "f32",
+    "f64", "bool", "char", "str", "String", "Int8", "Int16", "Int32", "Int64", "Int128", "UInt8",
+    "UInt16", "UInt32", "UInt64", "UInt128", "Float32", "Float64",
+];
 
-            serde_wasm_bindgen::to_value(&result)
-                .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
-        }
-        Err(err) => {
-            let result = CompileResult {
-                success: false,
-                ast: vec![],
-                errors: vec![convert_parse_error(&err)],
-                warnings: vec![],
-                metadata: CompileMetadata {
-                    version: env!("CARGO_PKG_VERSION").to_string(),
-                    gene_count: 0,
-                    trait_count: 0,
-                    constraint_count: 0,
-                    system_count: 0,
-                    function_count: 0,
-                    source_lines,
-                },
+/// Warn about type declarations whose name shadows a builtin type
+fn check_builtin_shadowing(nodes: &[AstNode]) -> Vec<Warning> {
+    nodes
+        .iter()
+        .filter_map(|node| {
+            let (kind, name) = match node {
+                AstNode::Gene { name, .. } => ("gene", name),
+                AstNode::Trait { name, .. } => ("trait", name),
+                AstNode::System { name, .. } => ("system", name),
+                _ => return None,
             };
+            BUILTIN_TYPES.contains(&name.as_str()).then(|| Warning {
+                kind: "ShadowsBuiltin",
+                line: node_line(node),
+                message: format!(
+                    "{} '{}' at line {} shadows the builtin type '{}'",
+                    kind,
+                    name,
+                    node_line(node),
+                    name
+                ),
+            })
+        })
+        .collect()
+}
 
-            serde_wasm_bindgen::to_value(&result)
-                .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+/// Warn about `Int`, `UInt` and `Float` types with a bit width DOL doesn't have
+///
+/// Only names in a type position count, so a field called `Int7` is fine.
+/// Valid widths are the ones listed in `BUILTIN_TYPES`.
+fn check_builtin_widths(source: &str) -> Vec<Warning> {
+    let lexemes: Vec<Lexeme> = scan(source)
+        .into_iter()
+        .filter(|lexeme| !lexeme.is_comment())
+        .collect();
+
+    let mut warnings = Vec::new();
+    for (i, lexeme) in lexemes.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|p| &lexemes[p]);
+        if lexeme.kind != LexKind::Word
+            || BUILTIN_TYPES.contains(&lexeme.text)
+            || reference_context(prev, lexemes.get(i + 1)) != "type"
+        {
+            continue;
         }
+        let Some(family) = ["UInt", "Int", "Float"].into_iter().find(|family| {
+            lexeme
+                .text
+                .strip_prefix(family)
+                .is_some_and(|width| !width.is_empty() && width.bytes().all(|b| b.is_ascii_digit()))
+        }) else {
+            continue;
+        };
+        let widths = if family == "Float" {
+            "32 or 64"
+        } else {
+            "8, 16, 32, 64 or 128"
+        };
+        warnings.push(Warning {
+            kind: "UnknownBuiltinWidth",
+            line: lexeme.line,
+            message: format!(
+                "unknown builtin type '{}' at line {}; {} takes {} bits",
+                lexeme.text, lexeme.line, family, widths
+            ),
+        });
     }
+    warnings
 }
 
-/// Parse and validate DOL source code
+/// Warn about constraints whose bodies are identical under different names
 ///
-/// Returns both the AST and validation results (warnings for empty exegesis, etc.)
-#[wasm_bindgen]
-pub fn compile_and_validate(source: &str) -> Result<JsValue, JsValue> {
-    let source_lines = source.lines().count();
+/// Bodies are compared as converted statements, so layout and comments
+/// don't matter. Constraints with empty bodies are skipped.
+fn check_duplicate_constraint_logic(nodes: &[AstNode]) -> Vec<Warning> {
+    // Body, line of its first constraint, and every constraint sharing it
+    let mut groups: Vec<(&[StatementNode], usize, Vec<String>)> = Vec::new();
+    for node in nodes {
+        let AstNode::Constraint {
+            name,
+            statements,
+            line,
+            ..
+        } = node
+        else {
+            continue;
+        };
+        if statements.is_empty() {
+            continue;
+        }
+        let member = format!("'{}' at line {}", name, line);
+        match groups
+            .iter_mut()
+            .find(|(body, _, _)| *body == statements.as_slice())
+        {
+            Some((_, _, members)) => members.push(member),
+            None => groups.push((statements, *line, vec![member])),
+        }
+    }
 
-    match parse_and_validate(source) {
-        Ok((decl, validation)) => {
-            let ast = vec![convert_declaration(&decl)];
+    groups
+        .into_iter()
+        .filter(|(_, _, members)| members.len() > 1)
+        .map(|(_, line, members)| Warning {
+            kind: "DuplicateConstraintLogic",
+            line,
+            message: format!(
+                "constraints {} have identical bodies; merge them or keep one",
+                members.join(", ")
+            ),
+        })
+        .collect()
+}
 
-            // Convert validation warnings to strings
-            let warnings: Vec<String> = validation.warnings.iter().map(|w| w.to_string()).collect();
+/// Report fields declared more than once in the same gene, trait or system
+///
+/// Converted statements carry no positions, so each repeat is located by
+/// scanning for its `has name:` within the declaration's lines, falling
+/// back to the declaration line.
+fn check_duplicate_fields(source: &str, nodes: &[AstNode]) -> Vec<CompileError> {
+    let lexemes = scan(source);
+    let mut errors = Vec::new();
 
-            let result = CompileResult {
-                success: validation.is_valid(),
-                ast,
-                errors: vec![],
-                warnings,
-                metadata: CompileMetadata {
-                    version: env!("CARGO_PKG_VERSION").to_string(),
-                    gene_count: if matches!(decl, Declaration::Gene(_)) {
-                        1
-                    } else {
-                        0
-                    },
-                    trait_count: if matches!(decl, Declaration::Trait(_)) {
-                        1
-                    } else {
-                        0
-                    },
-                    constraint_count: if matches!(decl, Declaration::Constraint(_)) {
-                        1
-                    } else {
-                        0
-                    },
-                    system_count: if matches!(decl, Declaration::System(_)) {
-                        1
-                    } else {
-                        0
-                    },
-                    function_count: if matches!(decl, Declaration::Function(_)) {
-                        1
-                    } else {
-                        0
-                    },
-                    source_lines,
-                },
-            };
+    for (i, node) in nodes.iter().enumerate() {
+        let (kind, name, statements) = match node {
+            AstNode::Gene {
+                name, statements, ..
+            } => ("gene", name, statements),
+            AstNode::Trait {
+                name, statements, ..
+            } => ("trait", name, statements),
+            AstNode::System {
+                name, statements, ..
+            } => ("system", name, statements),
+            _ => continue,
+        };
+        let start = node_line(node);
+        let end = nodes.get(i + 1).map_or(usize::MAX, node_line);
+        let sites: Vec<&Lexeme> = lexemes
+            .windows(3)
+            .filter(|w| {
+                w[0].kind == LexKind::Word
+                    && w[0].text == "has"
+                    && w[1].kind == LexKind::Word
+                    && w[2].is_punct(':')
+                    && (start..end).contains(&w[1].line)
+            })
+            .map(|w| &w[1])
+            .collect();
 
-            serde_wasm_bindgen::to_value(&result)
-                .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
-        }
-        Err(err) => {
-            let result = CompileResult {
-                success: false,
-                ast: vec![],
-                errors: vec![convert_parse_error(&err)],
-                warnings: vec![],
-                metadata: CompileMetadata {
-                    version: env!("CARGO_PKG_VERSION").to_string(),
-                    gene_count: 0,
-                    trait_count: 0,
-                    constraint_count: 0,
-                    system_count: 0,
-                    function_count: 0,
-                    source_lines,
-                },
+        let mut seen: BTreeMap<&str, usize> = BTreeMap::new();
+        for statement in statements {
+            let StatementNode::HasField { name: field, .. } = statement else {
+                continue;
             };
+            let count = seen.entry(field).or_default();
+            *count += 1;
+            if *count < 2 {
+                continue;
+            }
 
-            serde_wasm_bindgen::to_value(&result)
-                .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+            let site = sites.iter().filter(|l| l.text == field).nth(*count - 1);
+            let (line, column, end_line, end_column) = site.map_or((start, 1, start, 2), |l| {
+                (l.line, l.column, l.end_line(), l.end_column())
+            });
+            errors.push(CompileError {
+                message: format!(
+                    "field '{}' is declared more than once in {} '{}'",
+                    field, kind, name
+                ),
+                line,
+                column,
+                end_line,
+                end_column,
+                error_type: "DuplicateField".to_string(),
+            });
         }
     }
+    errors
 }
 
-/// Get the version of the DOL compiler
-#[wasm_bindgen]
-pub fn get_version() -> String {
-    env!("CARGO_PKG_VERSION").to_string()
+/// Semantic checks that fail a compile even though the source parsed
+fn check_errors(source: &str, nodes: &[AstNode]) -> Vec<CompileError> {
+    let mut errors = check_duplicate_fields(source, nodes);
+    errors.extend(check_invalid_versions(source, nodes));
+    errors
 }
 
-/// Validate DOL source without full compilation
-/// Returns true if the source is syntactically valid
-#[wasm_bindgen]
-pub fn validate_dol(source: &str) -> bool {
-    parse_file(source).is_ok()
+/// Always-on checks that warn without failing the compile
+fn check_warnings(source: &str, nodes: &[AstNode]) -> Vec<Warning> {
+    let mut warnings = check_builtin_shadowing(nodes);
+    warnings.extend(check_builtin_widths(source));
+    warnings.extend(check_duplicate_constraint_logic(nodes));
+    warnings.extend(check_chained_comparisons(source));
+    warnings.extend(check_unreachable_after_return(source));
+    warnings.extend(check_match_exhaustiveness(source));
+    warnings
 }
 
-/// Format DOL source code (stub for future implementation)
-#[wasm_bindgen]
-pub fn format_dol(source: &str) -> String {
-    // TODO: Implement proper formatting using metadol
-    source.to_string()
+/// 64-bit FNV-1a hash of a token sequence
+fn fnv1a<'a>(texts: impl IntoIterator<Item = &'a str>) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for text in texts {
+        // A separator byte keeps `ab c` and `a bc` apart
+        for byte in text.bytes().chain([0]) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+    hash
 }
 
-/// Result from compile_to_wasm containing bytecode or error
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct WasmBytecodeResult {
-    /// Whether compilation was successful
-    pub success: bool,
-    /// WASM bytecode (only present on success)
-    pub bytecode: Option<Vec<u8>>,
-    /// Error message (only present on failure)
-    pub error: Option<String>,
-    /// Size of generated bytecode in bytes
-    pub bytecode_size: usize,
+/// Fill in each declaration's checksum from its source tokens
+///
+/// A declaration spans from its line to the next declaration's, so a
+/// trailing docs block counts as part of it. Comments, whitespace and
+/// position are ignored; the tokens are hashed with 64-bit FNV-1a. Nodes
+/// must be in source order, which lets one pass over the tokens cover
+/// every declaration.
+fn assign_checksums(source: &str, nodes: &mut [AstNode]) {
+    let starts: Vec<usize> = nodes.iter().map(node_line).collect();
+    let mut lexemes = scan(source)
+        .into_iter()
+        .filter(|lexeme| !lexeme.is_comment())
+        .peekable();
+
+    for (i, node) in nodes.iter_mut().enumerate() {
+        let end = starts.get(i + 1).copied().unwrap_or(usize::MAX);
+        while lexemes.next_if(|l| l.line < starts[i]).is_some() {}
+
+        let hash = fnv1a(std::iter::from_fn(|| lexemes.next_if(|l| l.line < end)).map(|l| l.text));
+
+        match node {
+            AstNode::Gene { checksum, .. }
+            | AstNode::Trait { checksum, .. }
+            | AstNode::Constraint { checksum, .. }
+            | AstNode::System { checksum, .. }
+            | AstNode::Evolution { checksum, .. }
+            | AstNode::Function { checksum, .. }
+            | AstNode::Const { checksum, .. } => *checksum = format!("{:016x}", hash),
+        }
+    }
 }
 
-/// Compile DOL source code to WebAssembly bytecode
+/// Length of the comparison operator starting at `lexemes[i]`, if any
 ///
-/// This function parses DOL source, then uses the WasmCompiler to emit
-/// actual WASM bytecode that can be instantiated via the browser's WebAssembly API.
-///
-/// # Example (JavaScript)
-///
-/// ```javascript
-/// const result = compile_to_wasm(dolSource);
-/// if (result.success) {
-///     const wasmModule = await WebAssembly.compile(result.bytecode);
-///     const instance = await WebAssembly.instantiate(wasmModule);
-///     // Call exported functions...
-/// }
-/// ```
-#[wasm_bindgen]
-pub fn compile_to_wasm(source: &str) -> Result<JsValue, JsValue> {
-    // Parse the DOL source
-    match parse_file_all(source) {
-        Ok(declarations) => {
-            if declarations.is_empty() {
-                let result = WasmBytecodeResult {
-                    success: false,
-                    bytecode: None,
-                    error: Some("No declarations found in source".to_string()),
-                    bytecode_size: 0,
-                };
-                return serde_wasm_bindgen::to_value(&result)
-                    .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)));
-            }
+/// Operators are scanned one character at a time, so two-character ones
+/// are recognised from adjacent lexemes. Arrows (`->`, `=>`) and plain
+/// `=` don't count.
+fn comparison_len(lexemes: &[Lexeme], i: usize) -> Option<usize> {
+    let adjacent = |j: usize, c: char| {
+        lexemes
+            .get(j)
+            .is_some_and(|l| l.is_punct(c) && l.offset == lexemes[j - 1].offset + 1)
+    };
+    let lexeme = &lexemes[i];
+    if lexeme.is_punct('<') || lexeme.is_punct('>') {
+        let arrow = lexeme.is_punct('>')
+            && i > 0
+            && (lexemes[i - 1].is_punct('-') || lexemes[i - 1].is_punct('='))
+            && lexemes[i - 1].offset + 1 == lexeme.offset;
+        (!arrow).then(|| if adjacent(i + 1, '=') { 2 } else { 1 })
+    } else if (lexeme.is_punct('=') || lexeme.is_punct('!')) && adjacent(i + 1, '=') {
+        Some(2)
+    } else {
+        None
+    }
+}
 
-            // Filter to only function declarations (WASM compiler only supports functions)
-            let has_functions = declarations
-                .iter()
-                .any(|d| matches!(d, Declaration::Function(_)));
+/// Whether `lexemes[i]` starts a `&&` or `||` operator
+fn is_logical_operator(lexemes: &[Lexeme], i: usize) -> bool {
+    ['&', '|'].into_iter().any(|c| {
+        lexemes[i].is_punct(c)
+            && lexemes
+                .get(i + 1)
+                .is_some_and(|next| next.is_punct(c) && next.offset == lexemes[i].offset + 1)
+    })
+}
 
-            if !has_functions {
-                let result = WasmBytecodeResult {
-                    success: false,
-                    bytecode: None,
-                    error: Some("No function declarations found. WASM compilation requires at least one 'fun' declaration.".to_string()),
-                    bytecode_size: 0,
-                };
-                return serde_wasm_bindgen::to_value(&result)
-                    .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)));
+/// Index just past the `>` closing the type arguments opened at `lexemes[i]`
+///
+/// A `<` opens type arguments when the word before it sits in a type
+/// position (see `reference_context`) and a matching `>` follows on the
+/// same line with only words, numbers and commas in between, so
+/// `List<Option<i64>>` is skipped while `self.x < y < z` is not.
+fn type_args_end(lexemes: &[Lexeme], i: usize) -> Option<usize> {
+    let word = i.checked_sub(1).map(|w| &lexemes[w])?;
+    let before = i.checked_sub(2).map(|p| &lexemes[p]);
+    if !lexemes[i].is_punct('<')
+        || word.kind != LexKind::Word
+        || reference_context(before, Some(&lexemes[i])) != "type"
+    {
+        return None;
+    }
+    let mut depth = 0usize;
+    for (j, lexeme) in lexemes.iter().enumerate().skip(i) {
+        if lexeme.line != lexemes[i].line {
+            return None;
+        } else if lexeme.is_punct('<') {
+            depth += 1;
+        } else if lexeme.is_punct('>') {
+            depth -= 1;
+            if depth == 0 {
+                return Some(j + 1);
             }
+        } else if !matches!(lexeme.kind, LexKind::Word | LexKind::Number) && !lexeme.is_punct(',') {
+            return None;
+        }
+    }
+    None
+}
 
-            // Create a DolFile from the declarations to support multiple functions
-            let dol_file = DolFile {
-                module: None,
-                uses: Vec::new(),
-                declarations: declarations.clone(),
-            };
+/// Warn about chained comparisons like `a < b < c` in constraint and rule bodies
+///
+/// Token-level: two comparison operators on one line at the same
+/// parenthesis depth count as a chain unless a `&&`, `||` or `,` separates
+/// them; the angle brackets of type arguments aren't comparisons. Bodies of
+/// top-level `constraint`s and of `rule`s at any depth are checked.
+fn check_chained_comparisons(source: &str) -> Vec<Warning> {
+    let lexemes: Vec<Lexeme> = scan(source)
+        .into_iter()
+        .filter(|lexeme| !lexeme.is_comment())
+        .collect();
 
-            // Create WASM compiler with optimization enabled
-            let mut compiler = WasmCompiler::new().with_optimization(true);
+    let mut warnings = Vec::new();
+    let (mut depth, mut pending) = (0usize, false);
+    // Brace depth of the constraint or rule body being checked
+    let mut body: Option<usize> = None;
+    // Comparisons since the last separator on the current line, per parenthesis depth
+    let (mut line, mut per_depth, mut parens) = (0usize, vec![0usize], 0usize);
+    let mut i = 0;
+    while i < lexemes.len() {
+        let lexeme = &lexemes[i];
+        if lexeme.line != line {
+            line = lexeme.line;
+            per_depth = vec![0];
+            parens = 0;
+        }
 
-            // Compile ALL functions into a single WASM module
-            match compiler.compile_file(&dol_file) {
-                Ok(bytecode) => {
-                    let bytecode_size = bytecode.len();
-                    let result = WasmBytecodeResult {
-                        success: true,
-                        bytecode: Some(bytecode),
-                        error: None,
-                        bytecode_size,
-                    };
-                    serde_wasm_bindgen::to_value(&result)
-                        .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
-                }
-                Err(err) => {
-                    let result = WasmBytecodeResult {
-                        success: false,
-                        bytecode: None,
-                        error: Some(format!("WASM compilation error: {}", err)),
-                        bytecode_size: 0,
-                    };
-                    serde_wasm_bindgen::to_value(&result)
-                        .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
-                }
+        let checked = body.is_some();
+        if lexeme.is_punct('{') {
+            depth += 1;
+            if pending && !checked {
+                body = Some(depth);
             }
+            pending = false;
+        } else if lexeme.is_punct('}') {
+            if body == Some(depth) {
+                body = None;
+            }
+            depth = depth.saturating_sub(1);
+        } else if lexeme.kind == LexKind::Word
+            && (lexeme.text == "rule" || (depth == 0 && lexeme.text == "constraint"))
+            && lexemes
+                .get(i + 1)
+                .is_some_and(|name| name.kind == LexKind::Word)
+        {
+            pending = true;
+        } else if checked && lexeme.is_punct('(') {
+            parens += 1;
+            per_depth.resize(parens + 1, 0);
+        } else if checked && lexeme.is_punct(')') {
+            per_depth.truncate(parens.max(1));
+            parens = parens.saturating_sub(1);
+        } else if checked && (lexeme.is_punct(',') || is_logical_operator(&lexemes, i)) {
+            per_depth[parens] = 0;
+        } else if let Some(end) = type_args_end(&lexemes, i).filter(|_| checked) {
+            i = end;
+            continue;
+        } else if let Some(len) = comparison_len(&lexemes, i).filter(|_| checked) {
+            per_depth[parens] += 1;
+            if per_depth[parens] == 2 {
+                warnings.push(Warning {
+                    kind: "ChainedComparison",
+                    line,
+                    message: format!("chained comparison is ambiguous at line {}", line),
+                });
+            }
+            i += len;
+            continue;
         }
-        Err(err) => {
-            let result = WasmBytecodeResult {
-                success: false,
-                bytecode: None,
-                error: Some(format!("Parse error: {}", err)),
-                bytecode_size: 0,
-            };
-            serde_wasm_bindgen::to_value(&result)
-                .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
-        }
+        i += 1;
     }
+    warnings
 }
 
-/// Compile DOL source to WASM bytecode and return as Uint8Array
+/// Whether the line starting at `lexemes[start]` opens a match arm (`pattern => ...`)
+fn starts_match_arm(lexemes: &[Lexeme], start: usize) -> bool {
+    let line = lexemes[start].line;
+    let mut depth = 0usize;
+    for (i, lexeme) in lexemes.iter().enumerate().skip(start) {
+        if lexeme.line != line {
+            break;
+        } else if format::is_opener(lexeme) {
+            depth += 1;
+        } else if format::is_closer(lexeme) {
+            if depth == 0 {
+                break;
+            }
+            depth -= 1;
+        } else if depth == 0
+            && lexeme.is_punct('=')
+            && lexemes
+                .get(i + 1)
+                .is_some_and(|next| next.is_punct('>') && next.offset == lexeme.offset + 1)
+        {
+            return true;
+        }
+    }
+    false
+}
+
+/// Warn about statements following a `return` in the same block
+///
+/// Function bodies aren't in the converted AST, so this tracks bracket
+/// depth over tokens. After `return`, each later line starting at the same
+/// depth continues the returned expression if the previous line ends with
+/// an operator or opener; otherwise its first token is unreachable. A `,`
+/// or a new `pattern =>` line at that depth ends a match arm instead.
+fn check_unreachable_after_return(source: &str) -> Vec<Warning> {
+    let lexemes: Vec<Lexeme> = scan(source)
+        .into_iter()
+        .filter(|lexeme| !lexeme.is_comment())
+        .collect();
+
+    let mut warnings = Vec::new();
+    let mut depth = 0usize;
+    // Depth of the last `return` whose block or arm is still open
+    let mut returned: Option<usize> = None;
+    for (i, lexeme) in lexemes.iter().enumerate() {
+        if returned == Some(depth) {
+            let previous = &lexemes[i - 1];
+            if format::is_closer(lexeme) || lexeme.is_punct(',') {
+                returned = None;
+            } else if lexeme.line > previous.line
+                && (previous.kind != LexKind::Punct || format::is_closer(previous))
+            {
+                if !starts_match_arm(&lexemes, i) {
+                    warnings.push(Warning {
+                        kind: "UnreachableCode",
+                        line: lexeme.line,
+                        message: format!("unreachable code after return at line {}", lexeme.line),
+                    });
+                }
+                returned = None;
+            }
+        }
+
+        if format::is_opener(lexeme) {
+            depth += 1;
+        } else if format::is_closer(lexeme) {
+            depth = depth.saturating_sub(1);
+        } else if lexeme.kind == LexKind::Word && lexeme.text == "return" {
+            returned = Some(depth);
+        }
+    }
+    warnings
+}
+
+/// Warn about `match` blocks without a `_ => ...` arm
+///
+/// A heuristic: the converted AST has no match expressions and the
+/// scrutinee's variants aren't known here, so any match whose top-level
+/// arms lack a wildcard is flagged. A guarded `_ if ... =>` doesn't count.
+fn check_match_exhaustiveness(source: &str) -> Vec<Warning> {
+    let lexemes: Vec<Lexeme> = scan(source)
+        .into_iter()
+        .filter(|lexeme| !lexeme.is_comment())
+        .collect();
+
+    let mut warnings = Vec::new();
+    for (i, keyword) in lexemes.iter().enumerate() {
+        if keyword.kind != LexKind::Word || keyword.text != "match" {
+            continue;
+        }
+
+        // Skip the scrutinee to the block's opening brace
+        let mut depth = 0usize;
+        let mut arms = None;
+        for (j, lexeme) in lexemes.iter().enumerate().skip(i + 1) {
+            if lexeme.is_punct('{') && depth == 0 {
+                arms = Some(j + 1);
+                break;
+            } else if format::is_opener(lexeme) {
+                depth += 1;
+            } else if format::is_closer(lexeme) {
+                if depth == 0 {
+                    break;
+                }
+                depth -= 1;
+            }
+        }
+        let Some(arms) = arms else {
+            continue;
+        };
+
+        let mut depth = 0usize;
+        let mut wildcard = false;
+        for (j, lexeme) in lexemes.iter().enumerate().skip(arms) {
+            if format::is_closer(lexeme) {
+                if depth == 0 {
+                    break;
+                }
+                depth -= 1;
+            } else if format::is_opener(lexeme) {
+                depth += 1;
+            } else if depth == 0
+                && lexeme.kind == LexKind::Word
+                && lexeme.text == "_"
+                && lexemes.get(j + 1).is_some_and(|l| l.is_punct('='))
+                && lexemes.get(j + 2).is_some_and(|l| l.is_punct('>'))
+            {
+                wildcard = true;
+                break;
+            }
+        }
+        if !wildcard {
+            warnings.push(Warning {
+                kind: "NonExhaustiveMatch",
+                line: keyword.line,
+                message: format!("match may not be exhaustive at line {}", keyword.line),
+            });
+        }
+    }
+    warnings
+}
+
+/// Parse DOL source straight into browser-friendly nodes
+fn parse_nodes(source: &str) -> Result<Vec<AstNode>, ParseError> {
+    let declarations = parse_file_all(source)?;
+    Ok(declarations.iter().map(convert_declaration).collect())
+}
+
+/// Compile DOL source code to an AST
+///
+/// This is the main entry point for the WASM module.
+/// It parses the DOL source using metadol and returns a compilation result.
+#[wasm_bindgen]
+pub fn compile_dol(source: &str) -> Result<JsValue, JsValue> {
+    to_js(&compile(source))
+}
+
+/// Compile DOL source code and return the result as a JSON string
+///
+/// Same `CompileResult` as `compile_dol`, serialized with `serde_json`
+/// instead of `serde-wasm-bindgen`, for hosts that prefer raw JSON text
+/// (Node workers, Deno, wasmtime). Never throws: a serialization failure
+/// yields `{"error": "..."}`.
+#[wasm_bindgen]
+pub fn compile_dol_to_json(source: &str) -> String {
+    serde_json::to_string(&compile(source)).unwrap_or_else(|e| {
+        serde_json::json!({ "error": format!("Serialization error: {}", e) }).to_string()
+    })
+}
+
+/// Compile DOL source code and return the result as indented JSON
+///
+/// Like `compile_dol_to_json` but pretty-printed, for CLIs and logs where
+/// people read the output directly.
+#[wasm_bindgen]
+pub fn compile_dol_pretty(source: &str) -> String {
+    serde_json::to_string_pretty(&compile(source)).unwrap_or_else(|e| {
+        serde_json::json!({ "error": format!("Serialization error: {}", e) }).to_string()
+    })
+}
+
+/// Parse DOL source and return only the AST and errors
+///
+/// A lighter `compile_dol` for per-keystroke use such as syntax
+/// highlighting: it skips declaration counting, metadata and the warning
+/// checks, so `warnings` is always empty.
+#[wasm_bindgen]
+pub fn parse_dol(source: &str) -> Result<JsValue, JsValue> {
+    to_js(&parse(source))
+}
+
+/// Parse DOL source into a `ParseResult` without crossing the WASM boundary
+fn parse(source: &str) -> ParseResult {
+    match parse_nodes(source) {
+        Ok(mut ast) => {
+            assign_checksums(source, &mut ast);
+            ParseResult {
+                success: true,
+                ast,
+                errors: vec![],
+                warnings: vec![],
+            }
+        }
+        Err(err) => ParseResult {
+            success: false,
+            ast: vec![],
+            errors: vec![convert_parse_error(source, &err)],
+            warnings: vec![],
+        },
+    }
+}
+
+/// Parse DOL source into a `CompileResult` without crossing the WASM boundary
+fn compile(source: &str) -> CompileResult {
+    let (result, warnings) = check_source(source);
+    with_warnings(result, &warnings)
+}
+
+/// Parse and check `source`, keeping the warnings unrendered
+///
+/// The returned result's `warnings` is empty, so callers that add or
+/// promote warnings can still see their kinds and lines.
+fn check_source(source: &str) -> (CompileResult, Vec<Warning>) {
+    // Parse all declarations from the source
+    match parse_nodes(source) {
+        Ok(ast) => check_nodes(source, ast),
+        Err(err) => (
+            CompileResult {
+                success: false,
+                ast: vec![],
+                errors: vec![convert_parse_error(source, &err)],
+                warnings: vec![],
+                metadata: build_metadata(source, &[]),
+            },
+            vec![],
+        ),
+    }
+}
+
+/// Build a `CompileResult` from the parsed nodes of `source`
+fn compile_nodes(source: &str, ast: Vec<AstNode>) -> CompileResult {
+    let (result, warnings) = check_nodes(source, ast);
+    with_warnings(result, &warnings)
+}
+
+/// Run the checksums, counting and always-on checks shared by full and
+/// incremental compiles
+fn check_nodes(source: &str, mut ast: Vec<AstNode>) -> (CompileResult, Vec<Warning>) {
+    assign_checksums(source, &mut ast);
+    let errors = check_errors(source, &ast);
+    let warnings = check_warnings(source, &ast);
+
+    let result = CompileResult {
+        success: errors.is_empty(),
+        metadata: build_metadata(source, &ast),
+        ast,
+        errors,
+        warnings: vec![],
+    };
+    (result, warnings)
+}
+
+/// Render `warnings` into the result's `warnings` list
+fn with_warnings(mut result: CompileResult, warnings: &[Warning]) -> CompileResult {
+    result.warnings = warnings.iter().map(Warning::to_string).collect();
+    result
+}
+
+/// Count the declarations in `nodes`, parsed from `source`
+fn build_metadata(source: &str, nodes: &[AstNode]) -> CompileMetadata {
+    let counts = count_nodes(nodes);
+    CompileMetadata {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        gene_count: counts.genes,
+        trait_count: counts.traits,
+        constraint_count: counts.constraints,
+        system_count: counts.systems,
+        function_count: counts.functions,
+        field_count: counts.fields,
+        source_lines: source.lines().count(),
+    }
+}
+
+/// Declaration counts behind `CompileMetadata`
+#[derive(Debug, Default)]
+struct NodeCounts {
+    genes: usize,
+    traits: usize,
+    constraints: usize,
+    systems: usize,
+    functions: usize,
+    fields: usize,
+}
+
+impl AstVisitor for NodeCounts {
+    fn visit_gene(&mut self, node: &AstNode) {
+        self.genes += 1;
+        self.walk_statements(node);
+    }
+
+    fn visit_trait(&mut self, node: &AstNode) {
+        self.traits += 1;
+        self.walk_statements(node);
+    }
+
+    fn visit_constraint(&mut self, node: &AstNode) {
+        self.constraints += 1;
+        self.walk_statements(node);
+    }
+
+    fn visit_system(&mut self, node: &AstNode) {
+        self.systems += 1;
+        self.walk_statements(node);
+    }
+
+    /// Evolutions describe changes to another declaration, so their
+    /// statements would be counted twice
+    fn visit_evolution(&mut self, _node: &AstNode) {}
+
+    fn visit_function(&mut self, _node: &AstNode) {
+        self.functions += 1;
+    }
+
+    fn visit_const(&mut self, _node: &AstNode) {
+        self.functions += 1;
+    }
+
+    fn visit_field(&mut self, _name: &str, _field_type: &str) {
+        self.fields += 1;
+    }
+
+    fn visit_nested_function(&mut self, _name: &str, _purity: &str) {
+        self.functions += 1;
+    }
+}
+
+/// Count declarations, recursing into bodies for functions and fields
+fn count_nodes(nodes: &[AstNode]) -> NodeCounts {
+    let mut counts = NodeCounts::default();
+    counts.walk(nodes);
+    counts
+}
+
+/// Recompile after an edit, re-parsing only the declarations it touched
+///
+/// `previous_json` is the `compile_dol_to_json` output for the source
+/// before the edit, and `changed_start_line..=changed_end_line` is the
+/// edited range in the new source (1-based, inclusive).
+///
+/// Reuse heuristic: a declaration runs until the next one starts, and the
+/// edit's line shift is the change in total line count. Declarations
+/// before the range are reused as is, declarations after it are reused
+/// with their lines shifted, and the ones overlapping it are re-parsed from
+/// the new source. Falls back to a full compile when the previous result
+/// is missing, invalid or failed, when the range covers the whole file, or
+/// when the re-parsed region doesn't parse on its own.
+#[wasm_bindgen]
+pub fn recompile_dol(
+    source: &str,
+    previous_json: &str,
+    changed_start_line: usize,
+    changed_end_line: usize,
+) -> Result<JsValue, JsValue> {
+    let previous = serde_json::from_str(previous_json).ok();
+    to_js(&recompile(
+        source,
+        previous,
+        changed_start_line,
+        changed_end_line,
+    ))
+}
+
+/// Mutable access to a declaration's line
+fn node_line_mut(node: &mut AstNode) -> &mut usize {
+    match node {
+        AstNode::Gene { line, .. }
+        | AstNode::Trait { line, .. }
+        | AstNode::Constraint { line, .. }
+        | AstNode::System { line, .. }
+        | AstNode::Evolution { line, .. }
+        | AstNode::Function { line, .. }
+        | AstNode::Const { line, .. } => line,
+    }
+}
+
+/// Incremental compile behind `recompile_dol`
+fn recompile(
+    source: &str,
+    previous: Option<CompileResult>,
+    start: usize,
+    end: usize,
+) -> CompileResult {
+    let start = start.max(1);
+    let lines: Vec<&str> = source.split('\n').collect();
+    let total_lines = source.lines().count();
+    let Some(previous) = previous.filter(|p| p.success && !p.ast.is_empty()) else {
+        return compile(source);
+    };
+    if start <= 1 && end >= total_lines {
+        return compile(source);
+    }
+
+    let shift = total_lines as isize - previous.metadata.source_lines as isize;
+    let old_end = end.saturating_add_signed(-shift).max(start);
+    let starts: Vec<usize> = previous.ast.iter().map(node_line).collect();
+    // The declaration containing a line is the last one starting at or before it
+    let first = starts.iter().rposition(|&l| l <= start).unwrap_or(0);
+    let last = starts.iter().rposition(|&l| l <= old_end).unwrap_or(0);
+
+    let region_start = starts[first].min(start);
+    let region_end = starts
+        .get(last + 1)
+        .map_or(lines.len() + 1, |&l| l.saturating_add_signed(shift));
+    // Lines from an inconsistent `previous` can put the region before line 1
+    let (Some(from), Some(to)) = (region_start.checked_sub(1), region_end.checked_sub(1)) else {
+        return compile(source);
+    };
+    let Some(region) = lines.get(from..to) else {
+        return compile(source);
+    };
+    let Ok(declarations) = parse_file_all(&region.join("\n")) else {
+        return compile(source);
+    };
+
+    let mut ast: Vec<AstNode> = previous.ast[..first].to_vec();
+    ast.extend(declarations.iter().map(|decl| {
+        let mut node = convert_declaration(decl);
+        *node_line_mut(&mut node) += from;
+        node
+    }));
+    ast.extend(previous.ast[last + 1..].iter().map(|node| {
+        let mut node = node.clone();
+        let line = node_line_mut(&mut node);
+        *line = line.saturating_add_signed(shift);
+        node
+    }));
+    compile_nodes(source, ast)
+}
+
+/// Compile DOL source code with opt-in lints
+///
+/// `options` is a `CompileOptions` object; `undefined` or `null` behaves
+/// like `compile_dol`.
+#[wasm_bindgen]
+pub fn compile_dol_with_options(source: &str, options: JsValue) -> Result<JsValue, JsValue> {
+    let options: CompileOptions = if options.is_undefined() || options.is_null() {
+        CompileOptions::default()
+    } else {
+        serde_wasm_bindgen::from_value(options)
+            .map_err(|e| JsValue::from_str(&format!("Invalid options: {}", e)))?
+    };
+    to_js(&compile_with_options(source, &options))
+}
+
+/// Compile DOL source code at a `StrictnessLevel`
+///
+/// `level` is `"Lenient"`, `"Standard"` or `"Strict"`; `undefined` or
+/// `null` means `Strict`, where each warning is also reported as a
+/// `StrictWarning` error at the start of its line and `success` is false
+/// unless there are no errors or warnings.
+#[wasm_bindgen]
+pub fn compile_dol_strict(source: &str, level: JsValue) -> Result<JsValue, JsValue> {
+    let level = if level.is_undefined() || level.is_null() {
+        StrictnessLevel::Strict
+    } else {
+        serde_wasm_bindgen::from_value(level)
+            .map_err(|e| JsValue::from_str(&format!("Invalid strictness level: {}", e)))?
+    };
+    to_js(&compile_with_options(
+        source,
+        &CompileOptions::from_level(level),
+    ))
+}
+
+/// Copy every warning into the errors as a `StrictWarning`
+fn promote_warnings(mut result: CompileResult, warnings: &[Warning]) -> CompileResult {
+    let promoted = warnings.iter().map(|warning| CompileError {
+        message: warning.to_string(),
+        line: warning.line,
+        column: 1,
+        end_line: warning.line,
+        end_column: 2,
+        error_type: "StrictWarning".to_string(),
+    });
+    result.errors.extend(promoted);
+    result.success = result.errors.is_empty();
+    result
+}
+
+/// Compile and then run the lints enabled in `options`
+fn compile_with_options(source: &str, options: &CompileOptions) -> CompileResult {
+    let (mut result, mut warnings) = check_source(source);
+    warnings.extend(run_lints(source, &result.ast, options));
+
+    if let Some(first) = warnings.first().filter(|_| options.deny_warnings) {
+        // Warnings stay in the list so callers can still inspect them; the
+        // summary points at the first one
+        result.success = false;
+        result.errors.push(CompileError {
+            message: format!(
+                "compilation failed due to {} warning{} (deny_warnings)",
+                warnings.len(),
+                if warnings.len() == 1 { "" } else { "s" }
+            ),
+            line: first.line,
+            column: 1,
+            end_line: first.line,
+            end_column: 2,
+            error_type: "DenyWarnings".to_string(),
+        });
+    }
+    if options.warnings_as_errors {
+        result = promote_warnings(result, &warnings);
+    }
+    let mut result = with_warnings(result, &warnings);
+
+    // Trim last so counts and lints see the whole tree
+    if let Some(max_depth) = options.max_output_depth {
+        truncate_ast(&mut result.ast, max_depth);
+    }
+    result
+}
+
+/// Replace statement bodies deeper than `max_depth` with a `Truncated` marker
+///
+/// Declarations are depth 1 and their statements depth 2, so only a depth
+/// below 2 trims anything. Declarations themselves are always kept.
+fn truncate_ast(nodes: &mut [AstNode], max_depth: usize) {
+    if max_depth >= 2 {
+        return;
+    }
+    let truncate = |statements: &mut Vec<StatementNode>| {
+        if !statements.is_empty() {
+            let omitted = statements.len();
+            *statements = vec![StatementNode::Truncated { omitted }];
+        }
+    };
+    for node in nodes {
+        match node {
+            AstNode::Gene { statements, .. }
+            | AstNode::Trait { statements, .. }
+            | AstNode::Constraint { statements, .. }
+            | AstNode::System { statements, .. } => truncate(statements),
+            AstNode::Evolution {
+                additions,
+                deprecations,
+                ..
+            } => {
+                truncate(additions);
+                truncate(deprecations);
+            }
+            AstNode::Function { .. } | AstNode::Const { .. } => {}
+        }
+    }
+}
+
+/// Run every enabled lint over the source and its parsed AST
+fn run_lints(source: &str, nodes: &[AstNode], options: &CompileOptions) -> Vec<Warning> {
+    let mut warnings = Vec::new();
+    if let Some(max) = options.max_params {
+        warnings.extend(lint_max_params(nodes, max));
+    }
+    if let Some(max) = options.max_function_lines {
+        warnings.extend(lint_max_function_lines(source, max));
+    }
+    if options.prefer_line_comments {
+        warnings.extend(lint_line_comments(source));
+    }
+    if options.require_docs {
+        warnings.extend(lint_missing_docs(nodes));
+    }
+    if options.require_types {
+        warnings.extend(lint_missing_types(nodes));
+    }
+    if options.read_only_methods {
+        warnings.extend(lint_read_only_methods(
+            source,
+            nodes,
+            options.exempt_pub_getters,
+        ));
+    }
+    if options.duplicate_bodies {
+        warnings.extend(lint_duplicate_bodies(source));
+    }
+    if options.warn_magic_numbers {
+        warnings.extend(lint_magic_numbers(source, &options.magic_number_allowlist));
+    }
+    warnings
+}
+
+/// Warn about public declarations whose docs block is empty
+///
+/// Only declaration kinds that carry exegesis are checked; functions and
+/// constants have no attached docs in the AST.
+fn lint_missing_docs(nodes: &[AstNode]) -> Vec<Warning> {
+    nodes
+        .iter()
+        .filter_map(|node| {
+            let (kind, name, visibility) = match node {
+                AstNode::Gene {
+                    name, visibility, ..
+                } => ("gene", name, visibility),
+                AstNode::Trait {
+                    name, visibility, ..
+                } => ("trait", name, visibility),
+                AstNode::Constraint {
+                    name, visibility, ..
+                } => ("constraint", name, visibility),
+                AstNode::System {
+                    name, visibility, ..
+                } => ("system", name, visibility),
+                _ => return None,
+            };
+            let undocumented = declaration_doc(node).is_some_and(|doc| doc.trim().is_empty());
+            (visibility != "private" && undocumented).then(|| Warning {
+                kind: "MissingDocs",
+                line: node_line(node),
+                message: format!(
+                    "public {} '{}' at line {} has no documentation",
+                    kind,
+                    name,
+                    node_line(node)
+                ),
+            })
+        })
+        .collect()
+}
+
+/// Warn about constants without a type annotation
+fn lint_missing_types(nodes: &[AstNode]) -> Vec<Warning> {
+    nodes
+        .iter()
+        .filter_map(|node| match node {
+            AstNode::Const {
+                name,
+                const_type: None,
+                line,
+                ..
+            } => Some(Warning {
+                kind: "MissingType",
+                line: *line,
+                message: format!(
+                    "constant '{}' at line {} has no type annotation",
+                    name, line
+                ),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Warn about block comments that contain no newline
+fn lint_line_comments(source: &str) -> Vec<Warning> {
+    scan(source)
+        .iter()
+        .filter(|lexeme| lexeme.kind == LexKind::BlockComment && !lexeme.text.contains('\n'))
+        .map(|lexeme| Warning {
+            kind: "PreferLineComment",
+            line: lexeme.line,
+            message: format!(
+                "single-line block comment at line {}; prefer a `//` line comment",
+                lexeme.line
+            ),
+        })
+        .collect()
+}
+
+/// Warn about functions with more than `max` parameters
+fn lint_max_params(nodes: &[AstNode], max: usize) -> Vec<Warning> {
+    nodes
+        .iter()
+        .filter_map(|node| match node {
+            AstNode::Function {
+                name, params, line, ..
+            } if params.len() > max => Some(Warning {
+                kind: "TooManyParams",
+                line: *line,
+                message: format!(
+                    "function '{}' declares {} parameters (max {}) at line {}; \
+                     group them in a gene",
+                    name,
+                    params.len(),
+                    max,
+                    line
+                ),
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Warn about functions whose body spans more than `max` lines
+///
+/// Nested functions count too; lengths come from the token-level
+/// complexity report.
+fn lint_max_function_lines(source: &str, max: usize) -> Vec<Warning> {
+    let mut long: Vec<FunctionComplexity> = collect_function_complexity(source)
+        .into_iter()
+        .filter(|function| function.body_len > max)
+        .collect();
+    long.sort_by_key(|function| function.line);
+    long.into_iter()
+        .map(|function| Warning {
+            kind: "LongFunction",
+            line: function.line,
+            message: format!(
+                "function '{}' at line {} spans {} lines (max {}); split it up",
+                function.name, function.line, function.body_len, max
+            ),
+        })
+        .collect()
+}
+
+/// Warn about number literals in function bodies that deserve a name
+///
+/// 0 and 1 are always accepted (a leading `-` is a separate token, so -1
+/// is too), as is anything in `allowlist` with the same value. Statements
+/// starting with `const` are skipped, since naming the number is the fix.
+/// Nested functions are covered by their enclosing body's scan.
+fn lint_magic_numbers(source: &str, allowlist: &[String]) -> Vec<Warning> {
+    let lexemes: Vec<Lexeme> = scan(source)
+        .into_iter()
+        .filter(|lexeme| !lexeme.is_comment())
+        .collect();
+    let value = |text: &str| text.replace('_', "").parse::<f64>().ok();
+    let allowed = |text: &str| {
+        ["0", "1"]
+            .iter()
+            .copied()
+            .chain(allowlist.iter().map(String::as_str))
+            .any(|allowed| {
+                allowed == text || value(allowed).is_some_and(|v| Some(v) == value(text))
+            })
+    };
+
+    let mut warnings = Vec::new();
+    let mut scanned = 0;
+    for (i, lexeme) in lexemes.iter().enumerate() {
+        if i < scanned || lexeme.kind != LexKind::Word || lexeme.text != "fun" {
+            continue;
+        }
+        let Some(name) = lexemes.get(i + 1).filter(|n| n.kind == LexKind::Word) else {
+            continue;
+        };
+        let Some(open) = body_open(&lexemes, i + 2) else {
+            continue;
+        };
+        scanned = block_end(&lexemes, open);
+
+        let mut const_line = None;
+        for number in &lexemes[open + 1..scanned] {
+            if number.kind == LexKind::Word && number.text == "const" {
+                const_line = Some(number.line);
+            } else if number.kind == LexKind::Number
+                && const_line != Some(number.line)
+                && !allowed(number.text)
+            {
+                warnings.push(Warning {
+                    kind: "MagicNumber",
+                    line: number.line,
+                    message: format!(
+                        "magic number {} in function '{}' at line {}; give it a named const",
+                        number.text, name.text, number.line
+                    ),
+                });
+            }
+        }
+    }
+    warnings
+}
+
+/// Index of the brace closing the block opened at `open`, or the last lexeme
+fn block_end(lexemes: &[Lexeme], open: usize) -> usize {
+    let mut depth = 0usize;
+    for (i, lexeme) in lexemes.iter().enumerate().skip(open) {
+        if lexeme.is_punct('{') {
+            depth += 1;
+        } else if lexeme.is_punct('}') {
+            depth -= 1;
+            if depth == 0 {
+                return i;
+            }
+        }
+    }
+    lexemes.len().saturating_sub(1)
+}
+
+/// Warn about system methods that read fields but never write any
+///
+/// Method bodies aren't in the converted AST, so this scans each system's
+/// source for `fun` blocks. A field is accessed through `self.`/`this.` or
+/// by its bare name; it's written when followed by `=` or a compound
+/// assignment. `sex` methods are skipped since their effects may be
+/// elsewhere, and `pub` ones too when `exempt_pub` is set.
+fn lint_read_only_methods(source: &str, nodes: &[AstNode], exempt_pub: bool) -> Vec<Warning> {
+    let lexemes: Vec<Lexeme> = scan(source)
+        .into_iter()
+        .filter(|lexeme| !lexeme.is_comment())
+        .collect();
+    let word = |i: usize, text: &str| {
+        lexemes
+            .get(i)
+            .is_some_and(|l| l.kind == LexKind::Word && l.text == text)
+    };
+    let punct = |i: usize, c: char| lexemes.get(i).is_some_and(|l| l.is_punct(c));
+
+    let mut warnings = Vec::new();
+    for (i, lexeme) in lexemes.iter().enumerate() {
+        if lexeme.kind != LexKind::Word || lexeme.text != "system" {
+            continue;
+        }
+        let Some(fields) = lexemes.get(i + 1).and_then(|name| {
+            nodes.iter().find_map(|node| match node {
+                AstNode::System {
+                    name: n,
+                    statements,
+                    ..
+                } if n == name.text => Some(
+                    statements
+                        .iter()
+                        .filter_map(|stmt| match stmt {
+                            StatementNode::HasField { name, .. } => Some(name.as_str()),
+                            _ => None,
+                        })
+                        .collect::<Vec<_>>(),
+                ),
+                _ => None,
+            })
+        }) else {
+            continue;
+        };
+        let Some(open) = (i..lexemes.len()).find(|&j| punct(j, '{')) else {
+            continue;
+        };
+
+        let close = block_end(&lexemes, open);
+        let mut j = open + 1;
+        while j < close {
+            if !word(j, "fun") {
+                j += 1;
+                continue;
+            }
+            let effectful = word(j - 1, "sex");
+            let public = word(j - 1, "pub") || (effectful && word(j - 2, "pub"));
+            let Some(body) = (j..close).find(|&k| punct(k, '{')) else {
+                break;
+            };
+            let body_end = block_end(&lexemes, body);
+
+            let (mut reads, mut writes) = (0usize, 0usize);
+            for (k, token) in lexemes.iter().enumerate().take(body_end).skip(body + 1) {
+                // A field is either bare or reached through `self.`/`this.`
+                let dotted = punct(k - 1, '.');
+                let via_self = dotted && (word(k - 2, "self") || word(k - 2, "this"));
+                if token.kind != LexKind::Word
+                    || !fields.contains(&token.text)
+                    || (dotted && !via_self)
+                {
+                    continue;
+                }
+                let assigned = (punct(k + 1, '=') && !punct(k + 2, '='))
+                    || ("+-*/%".chars().any(|op| punct(k + 1, op)) && punct(k + 2, '='));
+                if assigned {
+                    writes += 1;
+                } else {
+                    reads += 1;
+                }
+            }
+
+            if reads > 0 && writes == 0 && !effectful && !(exempt_pub && public) {
+                if let Some(name) = lexemes.get(j + 1) {
+                    warnings.push(Warning {
+                        kind: "ReadOnlySpiritMethod",
+                        line: lexemes[j].line,
+                        message: format!(
+                            "method '{}' at line {} reads fields but never writes one; \
+                             consider a pure `fun` outside the system",
+                            name.text, lexemes[j].line
+                        ),
+                    });
+                }
+            }
+            j = body_end + 1;
+        }
+    }
+    warnings
+}
+
+/// Parse and validate DOL source code
+///
+/// Returns both the AST and validation results (warnings for empty exegesis, etc.)
+#[wasm_bindgen]
+pub fn compile_and_validate(source: &str) -> Result<JsValue, JsValue> {
+    match parse_and_validate(source) {
+        Ok((decl, validation)) => {
+            let mut ast = vec![convert_declaration(&decl)];
+            assign_checksums(source, &mut ast);
+
+            // Validation warnings are about the declaration as a whole
+            let warnings: Vec<Warning> = validation
+                .warnings
+                .iter()
+                .map(|w| Warning {
+                    kind: "Validation",
+                    line: node_line(&ast[0]),
+                    message: w.to_string(),
+                })
+                .collect();
+
+            let result = CompileResult {
+                success: validation.is_valid(),
+                metadata: build_metadata(source, &ast),
+                ast,
+                errors: vec![],
+                warnings: vec![],
+            };
+            let result = with_warnings(result, &warnings);
+
+            serde_wasm_bindgen::to_value(&result)
+                .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+        }
+        Err(err) => {
+            let result = CompileResult {
+                success: false,
+                ast: vec![],
+                errors: vec![convert_parse_error(source, &err)],
+                warnings: vec![],
+                metadata: build_metadata(source, &[]),
+            };
+
+            serde_wasm_bindgen::to_value(&result)
+                .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+        }
+    }
+}
+
+/// Get only the compile metadata (declaration counts and line count)
+///
+/// For project indexers that need counts but not the AST: skips the
+/// warning checks and serializes just the `CompileMetadata`. A source that
+/// fails to parse reports zero counts.
+#[wasm_bindgen]
+pub fn metadata_dol(source: &str) -> Result<JsValue, JsValue> {
+    to_js(&metadata(source))
+}
+
+/// Metadata for `source` without building a `CompileResult`
+fn metadata(source: &str) -> CompileMetadata {
+    build_metadata(source, &parse_nodes(source).unwrap_or_default())
+}
+
+/// Get the version of the DOL compiler
+#[wasm_bindgen]
+pub fn get_version() -> String {
+    env!("CARGO_PKG_VERSION").to_string()
+}
+
+/// One identifier renamed by `format_dol_canonical_case`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaseRename {
+    pub from: String,
+    pub to: String,
+}
+
+/// Output of `format_dol_canonical_case`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CanonicalCase {
+    pub formatted: String,
+    pub renames: Vec<CaseRename>,
+}
+
+/// Pick canonical names: PascalCase for type declarations, snake_case for
+/// functions and fields
+///
+/// The first declaration of a name decides its spelling, so a name used
+/// both as a type and a field is renamed once, consistently.
+fn collect_case_renames(nodes: &[AstNode]) -> Vec<CaseRename> {
+    let mut names: Vec<(String, String)> = Vec::new();
+    let mut add = |name: &str, canonical: String| {
+        if name != canonical && !names.iter().any(|(from, _)| from == name) {
+            names.push((name.to_string(), canonical));
+        }
+    };
+
+    for node in nodes {
+        let statements = match node {
+            AstNode::Gene {
+                name, statements, ..
+            }
+            | AstNode::Trait {
+                name, statements, ..
+            }
+            | AstNode::System {
+                name, statements, ..
+            } => {
+                add(name, format::to_pascal_case(name));
+                statements.as_slice()
+            }
+            AstNode::Function { name, .. } => {
+                add(name, format::to_snake_case(name));
+                continue;
+            }
+            _ => continue,
+        };
+        for statement in statements {
+            match statement {
+                StatementNode::HasField { name, .. } | StatementNode::Function { name, .. } => {
+                    add(name, format::to_snake_case(name))
+                }
+                _ => {}
+            }
+        }
+    }
+
+    names
+        .into_iter()
+        .map(|(from, to)| CaseRename { from, to })
+        .collect()
+}
+
+/// Rename declarations and their references to canonical casing, then format
+///
+/// Falls back to the unchanged source with no renames if the source doesn't
+/// compile, or if the renamed output no longer does (say, two names that
+/// collide once canonicalized).
+fn canonical_case(source: &str) -> CanonicalCase {
+    let compiles = |source: &str| {
+        parse_nodes(source)
+            .ok()
+            .filter(|nodes| check_errors(source, nodes).is_empty())
+    };
+    let unchanged = || CanonicalCase {
+        formatted: source.to_string(),
+        renames: vec![],
+    };
+
+    let Some(nodes) = compiles(source) else {
+        return unchanged();
+    };
+    let renames = collect_case_renames(&nodes);
+    let pairs: Vec<(String, String)> = renames
+        .iter()
+        .map(|r| (r.from.clone(), r.to.clone()))
+        .collect();
+    let formatted = format::format_source(&format::rename_identifiers(source, &pairs));
+    if compiles(&formatted).is_none() {
+        return unchanged();
+    }
+    CanonicalCase { formatted, renames }
+}
+
+/// Format DOL source with canonical identifier casing
+///
+/// Type declarations (genes, traits, systems) become PascalCase and
+/// functions and fields snake_case. Every reference is renamed along with
+/// its declaration, but comments, strings and docs are left alone. Source
+/// that does not compile, before or after renaming, is returned unchanged
+/// with no renames.
+#[wasm_bindgen]
+pub fn format_dol_canonical_case(source: &str) -> Result<JsValue, JsValue> {
+    to_js(&canonical_case(source))
+}
+
+/// Validate DOL source without full compilation
+/// Returns true if the source is syntactically valid
+///
+/// Runs the same parse and error checks as `compile_dol`, so the verdict
+/// always matches `compile_dol(source).success`, but skips counting,
+/// warnings and serialization entirely.
+#[wasm_bindgen]
+pub fn validate_dol(source: &str) -> bool {
+    parse_nodes(source).is_ok_and(|nodes| check_errors(source, &nodes).is_empty())
+}
+
+/// Format DOL source code
+///
+/// Re-indents with 4 spaces, trims trailing whitespace, collapses blank
+/// lines, separates top-level blocks with one blank line and aligns runs
+/// of `has name: Type` fields. Comments, strings and docs bodies are kept
+/// as written. Source that does not compile is returned unchanged.
+#[wasm_bindgen]
+pub fn format_dol(source: &str) -> String {
+    if !validate_dol(source) {
+        return source.to_string();
+    }
+    format::format_source(source)
+}
+
+/// Result of checking a JSON document against the `AstNode` schema
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AstValidationResult {
+    /// Whether the JSON deserializes into a `Vec<AstNode>`
+    pub valid: bool,
+    /// Serde error prefixed with the path of the offending value
+    pub error: Option<String>,
+}
+
+/// Check that `ast_json` deserializes into a `Vec<AstNode>`
+fn check_ast_json(ast_json: &str) -> AstValidationResult {
+    let deserializer = &mut serde_json::Deserializer::from_str(ast_json);
+    match serde_path_to_error::deserialize::<_, Vec<AstNode>>(deserializer) {
+        Ok(_) => AstValidationResult {
+            valid: true,
+            error: None,
+        },
+        Err(err) => AstValidationResult {
+            valid: false,
+            error: Some(format!("{}: {}", err.path(), err.inner())),
+        },
+    }
+}
+
+/// Validate a JSON-encoded AST produced outside the compiler
+///
+/// Useful for hosts that build `AstNode` trees programmatically and want to
+/// confirm they match the shape `compile_dol` emits before consuming them.
+#[wasm_bindgen]
+pub fn validate_ast_json(ast_json: &str) -> Result<JsValue, JsValue> {
+    to_js(&check_ast_json(ast_json))
+}
+
+/// A constraint and the fields its statements refer to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConstraintInfo {
+    pub name: String,
+    /// Gene that declares every field the constraint touches, if any
+    pub parent: Option<String>,
+    pub fields: Vec<String>,
+    pub line: usize,
+}
+
+/// Collect the field names touched by a list of statements, in first-use order
+fn referenced_fields(statements: &[StatementNode]) -> Vec<String> {
+    let mut fields: Vec<String> = Vec::new();
+    for stmt in statements {
+        let field = match stmt {
+            StatementNode::Has { property, .. } => property,
+            StatementNode::HasField { name, .. } => name,
+            StatementNode::Is { subject, .. }
+            | StatementNode::DerivesFrom { subject, .. }
+            | StatementNode::Requires { subject, .. }
+            | StatementNode::Matches { subject, .. }
+            | StatementNode::Never { subject, .. } => subject,
+            _ => continue,
+        };
+        let field = field
+            .strip_prefix("self.")
+            .or_else(|| field.strip_prefix("this."))
+            .unwrap_or(field);
+        if !fields.iter().any(|f| f == field) {
+            fields.push(field.to_string());
+        }
+    }
+    fields
+}
+
+/// Gather every constraint declaration with the fields it constrains
+///
+/// Constraints are top-level, so the parent is the first gene whose own
+/// statements cover all of the constraint's fields.
+fn collect_constraints(nodes: &[AstNode]) -> Vec<ConstraintInfo> {
+    let genes: Vec<(&String, Vec<String>)> = nodes
+        .iter()
+        .filter_map(|node| match node {
+            AstNode::Gene {
+                name, statements, ..
+            } => Some((name, referenced_fields(statements))),
+            _ => None,
+        })
+        .collect();
+
+    nodes
+        .iter()
+        .filter_map(|node| match node {
+            AstNode::Constraint {
+                name,
+                statements,
+                line,
+                ..
+            } => {
+                let fields = referenced_fields(statements);
+                let parent = genes
+                    .iter()
+                    .find(|(_, owned)| {
+                        !fields.is_empty() && fields.iter().all(|f| owned.contains(f))
+                    })
+                    .map(|(gene, _)| gene.to_string());
+                Some(ConstraintInfo {
+                    name: name.clone(),
+                    parent,
+                    fields,
+                    line: *line,
+                })
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// List all constraints in the source with the fields they touch
+///
+/// Returns an empty list if the source fails to parse.
+#[wasm_bindgen]
+pub fn list_constraints(source: &str) -> Result<JsValue, JsValue> {
+    let nodes = parse_nodes(source).unwrap_or_default();
+    to_js(&collect_constraints(&nodes))
+}
+
+/// Source excerpt surrounding a compile error
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ErrorContext {
+    /// Source lines from `start_line` through the trailing context, newline-joined
+    pub snippet: String,
+    /// 1-based line number of the first snippet line
+    pub start_line: usize,
+    /// 1-based line number the error points at
+    pub caret_line: usize,
+    /// 1-based column the error points at
+    pub caret_column: usize,
+}
+
+/// Cut the lines around `error` out of `source`
+fn build_error_context(source: &str, error: &CompileError, context_lines: usize) -> ErrorContext {
+    let lines: Vec<&str> = source.lines().collect();
+    let caret_line = error.line.clamp(1, lines.len().max(1));
+    let start_line = caret_line.saturating_sub(context_lines).max(1);
+    let end_line = (caret_line + context_lines).min(lines.len());
+
+    ErrorContext {
+        snippet: lines
+            .get(start_line - 1..end_line)
+            .unwrap_or_default()
+            .join("\n"),
+        start_line,
+        caret_line,
+        caret_column: error.column,
+    }
+}
+
+/// Get a compact source excerpt for the `error_index`-th compile error
+///
+/// Returns `null` when the source compiles cleanly or the index is out of range.
+#[wasm_bindgen]
+pub fn error_context(
+    source: &str,
+    error_index: usize,
+    context_lines: usize,
+) -> Result<JsValue, JsValue> {
+    let result = compile(source);
+    let context = result
+        .errors
+        .get(error_index)
+        .map(|error| build_error_context(source, error, context_lines));
+    to_js(&context)
+}
+
+/// Hover information for a declaration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HoverInfo {
+    pub signature: String,
+    /// Attached exegesis, if the declaration kind carries one
+    pub doc: Option<String>,
+}
+
+/// Render a declaration header the way it would be written in source
+fn declaration_signature(node: &AstNode) -> String {
+    let with_visibility = |visibility: &str, header: String| {
+        if visibility == "private" {
+            header
+        } else {
+            format!("{} {}", visibility, header)
+        }
+    };
+
+    match node {
+        AstNode::Gene {
+            name,
+            visibility,
+            extends,
+            ..
+        } => {
+            let header = match extends {
+                Some(parent) => format!("gen {} extends {}", name, parent),
+                None => format!("gen {}", name),
+            };
+            with_visibility(visibility, header)
+        }
+        AstNode::Trait {
+            name, visibility, ..
+        } => with_visibility(visibility, format!("trait {}", name)),
+        AstNode::Constraint {
+            name, visibility, ..
+        } => with_visibility(visibility, format!("rule {}", name)),
+        AstNode::System {
+            name,
+            visibility,
+            version,
+            ..
+        } => with_visibility(visibility, format!("system {} @ {}", name, version)),
+        AstNode::Evolution {
+            name,
+            version,
+            parent_version,
+            ..
+        } => format!("evo {} @ {} > {}", name, version, parent_version),
+        AstNode::Function {
+            name,
+            visibility,
+            purity,
+            params,
+            return_type,
+            ..
+        } => {
+            let params: Vec<String> = params
+                .iter()
+                .map(|p| format!("{}: {}", p.name, p.param_type))
+                .collect();
+            let keyword = if purity == "sex" { "sex fun" } else { "fun" };
+            let mut header = format!("{} {}({})", keyword, name, params.join(", "));
+            if let Some(ret) = return_type {
+                header.push_str(&format!(" -> {}", ret));
+            }
+            with_visibility(visibility, header)
+        }
+        AstNode::Const {
+            name,
+            visibility,
+            const_type,
+            ..
+        } => {
+            let header = match const_type {
+                Some(ty) => format!("const {}: {}", name, ty),
+                None => format!("const {}", name),
+            };
+            with_visibility(visibility, header)
+        }
+    }
+}
+
+/// Render a statement roughly as it appears in source
+fn statement_label(stmt: &StatementNode) -> String {
+    match stmt {
+        StatementNode::Has { subject, property } => format!("{} has {}", subject, property),
+        StatementNode::HasField {
+            name, field_type, ..
+        } => format!("has {}: {}", name, field_type),
+        StatementNode::Is { subject, state } => format!("{} is {}", subject, state),
+        StatementNode::DerivesFrom { subject, origin } => {
+            format!("{} derives from {}", subject, origin)
+        }
+        StatementNode::Requires {
+            subject,
+            requirement,
+        } => format!("{} requires {}", subject, requirement),
+        StatementNode::Uses { reference } => format!("uses {}", reference),
+        StatementNode::Emits { action, event } => format!("{} emits {}", action, event),
+        StatementNode::Matches { subject, target } => format!("{} matches {}", subject, target),
+        StatementNode::Never { subject, action } => format!("{} never {}", subject, action),
+        StatementNode::Quantified { quantifier, phrase } => format!("{} {}", quantifier, phrase),
+        StatementNode::Function {
+            name,
+            visibility,
+            purity,
+        } => {
+            let keyword = if purity == "sex" { "sex fun" } else { "fun" };
+            match visibility.as_str() {
+                "private" | "" => format!("{} {}", keyword, name),
+                _ => format!("{} {} {}", visibility, keyword, name),
+            }
+        }
+        StatementNode::Other { description } => description.clone(),
+        StatementNode::Truncated { omitted } => format!("... {} more", omitted),
+    }
+}
+
+/// Labels for the children shown beneath a declaration in a tree view
+fn child_labels(node: &AstNode) -> Vec<String> {
+    match node {
+        AstNode::Gene { statements, .. }
+        | AstNode::Trait { statements, .. }
+        | AstNode::Constraint { statements, .. }
+        | AstNode::System { statements, .. } => statements.iter().map(statement_label).collect(),
+        AstNode::Evolution {
+            additions,
+            deprecations,
+            removals,
+            ..
+        } => additions
+            .iter()
+            .map(|s| format!("adds {}", statement_label(s)))
+            .chain(
+                deprecations
+                    .iter()
+                    .map(|s| format!("deprecates {}", statement_label(s))),
+            )
+            .chain(removals.iter().map(|r| format!("removes {}", r)))
+            .collect(),
+        AstNode::Function { .. } | AstNode::Const { .. } => vec![],
+    }
+}
+
+/// Exegesis attached to a declaration, if its kind carries one
+fn declaration_doc(node: &AstNode) -> Option<&str> {
+    match node {
+        AstNode::Gene { exegesis, .. }
+        | AstNode::Trait { exegesis, .. }
+        | AstNode::Constraint { exegesis, .. }
+        | AstNode::System { exegesis, .. }
+        | AstNode::Evolution { exegesis, .. } => Some(exegesis),
+        AstNode::Function { .. } | AstNode::Const { .. } => None,
+    }
+}
+
+/// Source line a declaration starts on
+fn node_line(node: &AstNode) -> usize {
+    match node {
+        AstNode::Gene { line, .. }
+        | AstNode::Trait { line, .. }
+        | AstNode::Constraint { line, .. }
+        | AstNode::System { line, .. }
+        | AstNode::Evolution { line, .. }
+        | AstNode::Function { line, .. }
+        | AstNode::Const { line, .. } => *line,
+    }
+}
+
+/// Name a declaration is declared under
+fn node_name(node: &AstNode) -> &str {
+    match node {
+        AstNode::Gene { name, .. }
+        | AstNode::Trait { name, .. }
+        | AstNode::Constraint { name, .. }
+        | AstNode::System { name, .. }
+        | AstNode::Evolution { name, .. }
+        | AstNode::Function { name, .. }
+        | AstNode::Const { name, .. } => name,
+    }
+}
+
+/// Hover for a whole declaration: its header and docs
+fn declaration_hover(node: &AstNode) -> HoverInfo {
+    HoverInfo {
+        signature: declaration_signature(node),
+        doc: declaration_doc(node)
+            .map(str::trim)
+            .filter(|doc| !doc.is_empty())
+            .map(str::to_string),
+    }
+}
+
+/// Resolve the identifier at a 1-based `line` and `column`
+///
+/// The AST only records where declarations start, so the enclosing
+/// declaration is the last one starting at or before `line`. A field or
+/// nested function name resolves to that member of the enclosing
+/// declaration, and a declaration's name resolves to the declaration
+/// wherever it appears. Anything else hovers the enclosing declaration.
+fn hover_at(source: &str, nodes: &[AstNode], line: usize, column: usize) -> Option<HoverInfo> {
+    let enclosing = nodes
+        .iter()
+        .filter(|node| node_line(node) <= line)
+        .max_by_key(|node| node_line(node));
+    let lexemes = scan(source);
+    let Some(word) = lexemes.iter().find(|lexeme| {
+        lexeme.kind == LexKind::Word
+            && lexeme.line == line
+            && (lexeme.column..lexeme.end_column()).contains(&column)
+    }) else {
+        return enclosing.map(declaration_hover);
+    };
+
+    let statements = match enclosing {
+        Some(
+            AstNode::Gene { statements, .. }
+            | AstNode::Trait { statements, .. }
+            | AstNode::Constraint { statements, .. }
+            | AstNode::System { statements, .. },
+        ) => statements.as_slice(),
+        _ => &[],
+    };
+    let member = statements.iter().find(|statement| match statement {
+        StatementNode::HasField { name, .. } | StatementNode::Function { name, .. } => {
+            name == word.text
+        }
+        _ => false,
+    });
+    if let Some(member) = member {
+        return Some(HoverInfo {
+            signature: statement_label(member),
+            doc: None,
+        });
+    }
+
+    nodes
+        .iter()
+        .find(|node| node_name(node) == word.text)
+        .or(enclosing)
+        .map(declaration_hover)
+}
+
+/// Get the signature and documentation of the identifier at a position
+///
+/// `line` and `column` are 1-based. Fields and nested functions resolve
+/// to themselves, declaration names to their declaration, and anything
+/// else to the enclosing declaration. Returns `null` if nothing encloses
+/// the position or the source fails to parse.
+#[wasm_bindgen]
+pub fn hover(source: &str, line: usize, column: usize) -> Result<JsValue, JsValue> {
+    let nodes = parse_nodes(source).unwrap_or_default();
+    to_js(&hover_at(source, &nodes, line, column))
+}
+
+/// Render compile errors as a fixed-width table sorted by position
+fn render_errors_table(errors: &[CompileError]) -> String {
+    if errors.is_empty() {
+        return "No errors.".to_string();
+    }
+
+    let mut sorted: Vec<&CompileError> = errors.iter().collect();
+    sorted.sort_by_key(|e| (e.line, e.column));
+
+    let header = ["LINE", "COL", "TYPE", "MESSAGE"];
+    let rows: Vec<[String; 4]> = sorted
+        .iter()
+        .map(|e| {
+            [
+                e.line.to_string(),
+                e.column.to_string(),
+                e.error_type.clone(),
+                e.message.clone(),
+            ]
+        })
+        .collect();
+
+    // The message column is last and left unpadded
+    let mut widths = header.map(str::len);
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row.iter()).take(3) {
+            *width = (*width).max(cell.len());
+        }
+    }
+
+    let format_row = |cells: [&str; 4]| {
+        format!(
+            "{:<w0$}  {:<w1$}  {:<w2$}  {}",
+            cells[0],
+            cells[1],
+            cells[2],
+            cells[3],
+            w0 = widths[0],
+            w1 = widths[1],
+            w2 = widths[2],
+        )
+    };
+
+    let mut lines = vec![format_row(header)];
+    for row in &rows {
+        lines.push(format_row([
+            row[0].as_str(),
+            row[1].as_str(),
+            row[2].as_str(),
+            row[3].as_str(),
+        ]));
+    }
+    lines.join("\n")
+}
+
+/// Compile the source and render its errors as a plain-text table
+///
+/// Intended for CLI hosts; returns "No errors." for clean sources.
+#[wasm_bindgen]
+pub fn errors_table(source: &str) -> String {
+    render_errors_table(&compile(source).errors)
+}
+
+/// Parse a semantic version, returning `(major, minor, patch)`
+///
+/// Accepts `MAJOR.MINOR.PATCH` with optional `-prerelease` and `+build`
+/// suffixes, per semver 2.0.0. Numeric components may not carry leading
+/// zeros.
+fn parse_semver(version: &str) -> Option<(u32, u32, u32)> {
+    let is_identifier = |part: &str| {
+        !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    };
+    let is_number = |part: &str| {
+        !part.is_empty()
+            && part.chars().all(|c| c.is_ascii_digit())
+            && (part == "0" || !part.starts_with('0'))
+    };
+
+    let (rest, build) = match version.split_once('+') {
+        Some((rest, build)) => (rest, Some(build)),
+        None => (version, None),
+    };
+    let (core, prerelease) = match rest.split_once('-') {
+        Some((core, prerelease)) => (core, Some(prerelease)),
+        None => (rest, None),
+    };
+
+    if let Some(build) = build {
+        if !build.split('.').all(is_identifier) {
+            return None;
+        }
+    }
+    if let Some(prerelease) = prerelease {
+        let valid = prerelease.split('.').all(|part| {
+            is_identifier(part) && (!part.chars().all(|c| c.is_ascii_digit()) || is_number(part))
+        });
+        if !valid {
+            return None;
+        }
+    }
+
+    let parts: Vec<&str> = core.split('.').collect();
+    if parts.len() != 3 || !parts.iter().all(|part| is_number(part)) {
+        return None;
+    }
+    Some((
+        parts[0].parse().ok()?,
+        parts[1].parse().ok()?,
+        parts[2].parse().ok()?,
+    ))
+}
+
+/// Semver check result for one versioned system
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VersionCheck {
+    pub system: String,
+    pub version: String,
+    pub valid: bool,
+    pub line: usize,
+}
+
+/// Check the version of every system declaration
+fn check_versions(nodes: &[AstNode]) -> Vec<VersionCheck> {
+    nodes
+        .iter()
+        .filter_map(|node| match node {
+            AstNode::System {
+                name,
+                version,
+                line,
+                ..
+            } => Some(VersionCheck {
+                system: name.clone(),
+                version: version.clone(),
+                valid: parse_semver(version).is_some(),
+                line: *line,
+            }),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Report systems whose version is not valid semver as compile errors
+fn check_invalid_versions(source: &str, nodes: &[AstNode]) -> Vec<CompileError> {
+    let lexemes = scan(source);
+    check_versions(nodes)
+        .into_iter()
+        .filter(|check| !check.valid)
+        .map(|check| {
+            // Point at the version following the first `@` on the declaration line
+            let version_start = lexemes
+                .iter()
+                .position(|l| l.line == check.line && l.is_punct('@'))
+                .and_then(|i| lexemes.get(i + 1))
+                .filter(|l| l.line == check.line);
+            let (column, end_column) = version_start.map_or((1, 2), |l| {
+                (l.column, l.column + check.version.chars().count())
+            });
+            CompileError {
+                message: format!(
+                    "system '{}' has invalid version '{}'; expected MAJOR.MINOR.PATCH",
+                    check.system, check.version
+                ),
+                line: check.line,
+                column,
+                end_line: check.line,
+                end_column,
+                error_type: "InvalidVersion".to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Report whether each system's version is valid semver
+///
+/// Narrower than a full compile: only the versions are checked. Returns
+/// an empty list if the source fails to parse.
+#[wasm_bindgen]
+pub fn validate_versions(source: &str) -> Result<JsValue, JsValue> {
+    to_js(&collect_version_checks(source))
+}
+
+/// Parse `source` and check its system versions, or nothing if it fails to parse
+fn collect_version_checks(source: &str) -> Vec<VersionCheck> {
+    let nodes = parse_nodes(source).unwrap_or_default();
+    check_versions(&nodes)
+}
+
+/// Keywords that introduce a named declaration or member
+const DECLARATION_KEYWORDS: &[&str] = &[
+    "gen",
+    "gene",
+    "trait",
+    "rule",
+    "constraint",
+    "system",
+    "evo",
+    "evolves",
+    "fun",
+    "const",
+    "has",
+    "type",
+];
+
+/// One occurrence of an identifier in the source
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Reference {
+    pub line: usize,
+    pub column: usize,
+    /// `declaration`, `type`, `call`, `field_access` or `reference`
+    pub context: String,
+}
+
+/// Classify how the identifier between `prev` and `next` is being used
+fn reference_context(prev: Option<&Lexeme>, next: Option<&Lexeme>) -> &'static str {
+    match prev {
+        Some(p) if p.kind == LexKind::Word && DECLARATION_KEYWORDS.contains(&p.text) => {
+            "declaration"
+        }
+        Some(p) if p.kind == LexKind::Word && p.text == "extends" => "type",
+        Some(p) if p.is_punct(':') || p.is_punct('>') || p.is_punct('<') => "type",
+        Some(p) if p.is_punct('.') => "field_access",
+        _ if next.is_some_and(|n| n.is_punct('(')) => "call",
+        _ => "reference",
+    }
+}
+
+/// Find every occurrence of the identifier `name` outside comments and strings
+fn collect_references(source: &str, name: &str) -> Vec<Reference> {
+    let lexemes: Vec<Lexeme> = scan(source)
+        .into_iter()
+        .filter(|lexeme| !lexeme.is_comment())
+        .collect();
+
+    lexemes
+        .iter()
+        .enumerate()
+        .filter(|(_, lexeme)| lexeme.kind == LexKind::Word && lexeme.text == name)
+        .map(|(i, lexeme)| {
+            let prev = i.checked_sub(1).map(|p| &lexemes[p]);
+            Reference {
+                line: lexeme.line,
+                column: lexeme.column,
+                context: reference_context(prev, lexemes.get(i + 1)).to_string(),
+            }
+        })
+        .collect()
+}
+
+/// Find all references to an identifier, for find-all-references in editors
+///
+/// This is a token-level scan, so it works on sources that fail to parse.
+#[wasm_bindgen]
+pub fn find_references(source: &str, name: &str) -> Result<JsValue, JsValue> {
+    to_js(&collect_references(source, name))
+}
+
+/// Genes named in exactly one type position across the source
+///
+/// Uses are counted with `collect_references`, so only `type` contexts
+/// (after `:`, `extends` or inside `<>`) count; comments, strings and the
+/// declaration itself don't. Empty if the source fails to parse.
+fn collect_single_use_types(source: &str) -> Vec<String> {
+    parse_nodes(source)
+        .unwrap_or_default()
+        .into_iter()
+        .filter_map(|node| match node {
+            AstNode::Gene { name, .. } => Some(name),
+            _ => None,
+        })
+        .filter(|name| {
+            collect_references(source, name)
+                .iter()
+                .filter(|reference| reference.context == "type")
+                .count()
+                == 1
+        })
+        .collect()
+}
+
+/// Find genes referenced as a type exactly once, as candidates for inlining
+#[wasm_bindgen]
+pub fn single_use_types(source: &str) -> Result<JsValue, JsValue> {
+    to_js(&collect_single_use_types(source))
+}
+
+/// Reserved words that never introduce a declaration
+const OTHER_KEYWORDS: &[&str] = &[
+    "is", "derives", "from", "requires", "uses", "emits", "matches", "never", "each", "all", "pub",
+    "sex", "extends", "match", "if", "else", "for", "while", "loop", "return", "let", "true",
+    "false", "docs", "exegesis",
+];
+
+/// A reserved word used where a declaration or field name belongs
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeywordMisuse {
+    pub keyword: String,
+    pub line: usize,
+    pub column: usize,
+    pub suggestion: String,
+}
+
+/// Find keywords sitting in the name slot after a declaration keyword or `has`
+fn collect_keyword_misuse(source: &str) -> Vec<KeywordMisuse> {
+    let lexemes: Vec<Lexeme> = scan(source)
+        .into_iter()
+        .filter(|lexeme| !lexeme.is_comment())
+        .collect();
+
+    lexemes
+        .windows(2)
+        .filter(|pair| {
+            let is_keyword =
+                |text: &str| DECLARATION_KEYWORDS.contains(&text) || OTHER_KEYWORDS.contains(&text);
+            pair[0].kind == LexKind::Word
+                && DECLARATION_KEYWORDS.contains(&pair[0].text)
+                && pair[1].kind == LexKind::Word
+                && is_keyword(pair[1].text)
+        })
+        .map(|pair| KeywordMisuse {
+            keyword: pair[1].text.to_string(),
+            line: pair[1].line,
+            column: pair[1].column,
+            suggestion: format!("rename it or escape it as `{}`", pair[1].text),
+        })
+        .collect()
+}
+
+/// Find reserved words used as declaration or field names
+///
+/// This is a token-level scan, so it also works on sources that fail to
+/// parse because of the collision.
+#[wasm_bindgen]
+pub fn keyword_misuse(source: &str) -> Result<JsValue, JsValue> {
+    to_js(&collect_keyword_misuse(source))
+}
+
+/// A closing bracket with no open bracket of its kind before it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct UnmatchedCloser {
+    pub delimiter: String,
+    pub line: usize,
+    pub column: usize,
+}
+
+/// Open bracket counts for live editor feedback
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BracketBalance {
+    /// Unclosed `{`
+    pub braces: usize,
+    /// Unclosed `(`
+    pub parens: usize,
+    /// Unclosed `[`
+    pub brackets: usize,
+    pub first_unmatched: Option<UnmatchedCloser>,
+}
+
+/// Count unclosed brackets of each kind, skipping comments, strings and docs
+fn compute_bracket_balance(source: &str) -> BracketBalance {
+    let mut balance = BracketBalance::default();
+    for lexeme in scan(source) {
+        if lexeme.kind != LexKind::Punct {
+            continue;
+        }
+        let count = match lexeme.text {
+            "{" | "}" => &mut balance.braces,
+            "(" | ")" => &mut balance.parens,
+            "[" | "]" => &mut balance.brackets,
+            _ => continue,
+        };
+        if matches!(lexeme.text, "{" | "(" | "[") {
+            *count += 1;
+        } else if *count > 0 {
+            *count -= 1;
+        } else if balance.first_unmatched.is_none() {
+            balance.first_unmatched = Some(UnmatchedCloser {
+                delimiter: lexeme.text.to_string(),
+                line: lexeme.line,
+                column: lexeme.column,
+            });
+        }
+    }
+    balance
+}
+
+/// Get the number of unclosed braces, parens and brackets
+///
+/// A single token-level pass meant to run on every keystroke; it works
+/// on sources that fail to parse.
+#[wasm_bindgen]
+pub fn bracket_balance(source: &str) -> Result<JsValue, JsValue> {
+    to_js(&compute_bracket_balance(source))
+}
+
+/// Size and shape of one function, for complexity dashboards
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FunctionComplexity {
+    pub name: String,
+    /// Declaration the function is nested in, if any
+    pub enclosing: Option<String>,
+    pub line: usize,
+    /// Lines spanned by the body, braces included
+    pub body_len: usize,
+    pub param_count: usize,
+    /// Deepest block nesting inside the body; 0 for a flat body
+    pub nesting_depth: usize,
+}
+
+impl FunctionComplexity {
+    fn score(&self) -> usize {
+        self.body_len + self.nesting_depth * 10
+    }
+}
+
+/// Index of the `{` opening a function body, scanning from `lexemes[from]`
+///
+/// Skips the rest of the signature; a closing brace or another declaration
+/// first means the function has no body.
+fn body_open(lexemes: &[Lexeme], from: usize) -> Option<usize> {
+    for (i, lexeme) in lexemes.iter().enumerate().skip(from) {
+        if lexeme.is_punct('{') {
+            return Some(i);
+        } else if lexeme.is_punct('}')
+            || (lexeme.kind == LexKind::Word && DECLARATION_KEYWORDS.contains(&lexeme.text))
+        {
+            return None;
+        }
+    }
+    None
+}
+
+/// Measure the function whose name is at `lexemes[name]`
+///
+/// Returns `(param_count, body_len, nesting_depth)`, or `None` for a
+/// signature without a body.
+fn measure_function(lexemes: &[Lexeme], name: usize) -> Option<(usize, usize, usize)> {
+    let mut i = name + 1;
+    let mut param_count = 0;
+    if lexemes.get(i)?.is_punct('(') {
+        let open = i;
+        let (mut parens, mut angles) = (0usize, 0usize);
+        loop {
+            let lexeme = lexemes.get(i)?;
+            if lexeme.is_punct('(') {
+                parens += 1;
+            } else if lexeme.is_punct(')') {
+                parens -= 1;
+                if parens == 0 {
+                    break;
+                }
+            } else if lexeme.is_punct('<') {
+                angles += 1;
+            } else if lexeme.is_punct('>') {
+                angles = angles.saturating_sub(1);
+            } else if lexeme.is_punct(',') && parens == 1 && angles == 0 {
+                param_count += 1;
+            }
+            i += 1;
+        }
+        // `n` commas separate `n + 1` parameters unless the list is empty
+        if i > open + 1 {
+            param_count += 1;
+        }
+    }
+
+    let i = body_open(lexemes, i)?;
+    let open = &lexemes[i];
+    let (mut depth, mut max_depth) = (0usize, 0usize);
+    let mut close = lexemes.last()?;
+    for lexeme in &lexemes[i..] {
+        if lexeme.is_punct('{') {
+            depth += 1;
+            max_depth = max_depth.max(depth);
+        } else if lexeme.is_punct('}') {
+            depth -= 1;
+            if depth == 0 {
+                close = lexeme;
+                break;
+            }
+        }
+    }
+    Some((param_count, close.line - open.line + 1, max_depth - 1))
+}
+
+/// Measure every function in the source, most complex first
+///
+/// Works on tokens, since function bodies are not part of the converted
+/// AST. The score used for ordering is `body_len + nesting_depth * 10`.
+fn collect_function_complexity(source: &str) -> Vec<FunctionComplexity> {
+    let lexemes: Vec<Lexeme> = scan(source)
+        .into_iter()
+        .filter(|lexeme| !lexeme.is_comment())
+        .collect();
+
+    let mut report = Vec::new();
+    let mut depth = 0usize;
+    let mut container: Option<&str> = None;
+    for (i, lexeme) in lexemes.iter().enumerate() {
+        if lexeme.is_punct('{') {
+            depth += 1;
+        } else if lexeme.is_punct('}') {
+            depth = depth.saturating_sub(1);
+        }
+        let Some(name) = lexemes.get(i + 1).filter(|n| n.kind == LexKind::Word) else {
+            continue;
+        };
+        if lexeme.kind != LexKind::Word {
+            continue;
+        }
+
+        if depth == 0
+            && matches!(
+                lexeme.text,
+                "gen" | "gene" | "trait" | "rule" | "constraint" | "system"
+            )
+        {
+            container = Some(name.text);
+        } else if lexeme.text == "fun" {
+            if let Some((param_count, body_len, nesting_depth)) = measure_function(&lexemes, i + 1)
+            {
+                report.push(FunctionComplexity {
+                    name: name.text.to_string(),
+                    enclosing: container.filter(|_| depth > 0).map(str::to_string),
+                    line: lexeme.line,
+                    body_len,
+                    param_count,
+                    nesting_depth,
+                });
+            }
+        }
+    }
+
+    report.sort_by_key(|f| std::cmp::Reverse(f.score()));
+    report
+}
+
+/// Report every function's size, parameters and nesting, most complex first
+///
+/// This is a token-level scan, so it works on sources that fail to parse.
+#[wasm_bindgen]
+pub fn complexity_report(source: &str) -> Result<JsValue, JsValue> {
+    to_js(&collect_function_complexity(source))
+}
+
+/// Functions sharing one body, for extract-function hints
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DuplicateBodies {
+    /// FNV-1a hash of the body's tokens, as 16 hex digits
+    pub hash: String,
+    pub functions: Vec<String>,
+    /// Line of each function's `fun`, in the same order as `functions`
+    pub lines: Vec<usize>,
+}
+
+/// Group functions whose bodies are identical token for token
+///
+/// Only the tokens between the body's braces are hashed, so names,
+/// parameters, layout and comments don't matter. Empty bodies are skipped.
+/// Groups are in source order of their first function.
+fn collect_duplicate_bodies(source: &str) -> Vec<DuplicateBodies> {
+    let lexemes: Vec<Lexeme> = scan(source)
+        .into_iter()
+        .filter(|lexeme| !lexeme.is_comment())
+        .collect();
+
+    let mut groups: Vec<DuplicateBodies> = Vec::new();
+    for (i, lexeme) in lexemes.iter().enumerate() {
+        if lexeme.kind != LexKind::Word || lexeme.text != "fun" {
+            continue;
+        }
+        let Some(name) = lexemes.get(i + 1).filter(|n| n.kind == LexKind::Word) else {
+            continue;
+        };
+        let Some(open) = body_open(&lexemes, i + 2) else {
+            continue;
+        };
+        let close = block_end(&lexemes, open);
+        if close <= open + 1 {
+            continue;
+        }
+
+        let hash = format!(
+            "{:016x}",
+            fnv1a(lexemes[open + 1..close].iter().map(|l| l.text))
+        );
+        match groups.iter_mut().find(|group| group.hash == hash) {
+            Some(group) => {
+                group.functions.push(name.text.to_string());
+                group.lines.push(lexeme.line);
+            }
+            None => groups.push(DuplicateBodies {
+                hash,
+                functions: vec![name.text.to_string()],
+                lines: vec![lexeme.line],
+            }),
+        }
+    }
+    groups.retain(|group| group.functions.len() > 1);
+    groups
+}
+
+/// Find functions with identical bodies that could share one definition
+///
+/// A token-level scan, so it works on sources that fail to parse.
+#[wasm_bindgen]
+pub fn duplicate_bodies(source: &str) -> Result<JsValue, JsValue> {
+    to_js(&collect_duplicate_bodies(source))
+}
+
+/// Warn about each function whose body repeats an earlier one
+fn lint_duplicate_bodies(source: &str) -> Vec<Warning> {
+    let mut warnings: Vec<Warning> = collect_duplicate_bodies(source)
+        .into_iter()
+        .flat_map(|group| {
+            let (first, first_line) = (group.functions[0].clone(), group.lines[0]);
+            group
+                .functions
+                .into_iter()
+                .zip(group.lines)
+                .skip(1)
+                .map(move |(name, line)| Warning {
+                    kind: "DuplicateBody",
+                    line,
+                    message: format!(
+                        "function '{}' at line {} has the same body as '{}' at line {}; \
+                         extract a shared function",
+                        name, line, first, first_line
+                    ),
+                })
+        })
+        .collect();
+    warnings.sort_by_key(|warning| warning.line);
+    warnings
+}
+
+/// File-level aggregate of the complexity metrics
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ComplexitySummary {
+    /// Declarations plus the statements in their bodies
+    pub total_nodes: usize,
+    /// Deepest `{}` nesting anywhere in the file
+    pub max_depth: usize,
+    /// Functions with a body, nested ones included
+    pub function_count: usize,
+    pub avg_function_complexity: f64,
+    pub max_function_complexity: usize,
+    pub longest_function_lines: usize,
+    /// Lines holding anything other than whitespace and comments
+    pub sloc: usize,
+}
+
+/// Counts the statements in declaration bodies
+#[derive(Default)]
+struct StatementCount(usize);
+
+impl AstVisitor for StatementCount {
+    fn visit_statement(&mut self, _statement: &StatementNode) {
+        self.0 += 1;
+    }
+}
+
+/// Aggregate the per-function report with node counts, nesting and SLOC
+///
+/// Node counts need a successful parse and are 0 otherwise; everything
+/// else comes from tokens. Function complexity is the report's score.
+fn summarize_complexity(source: &str) -> ComplexitySummary {
+    let nodes = parse_nodes(source).unwrap_or_default();
+    let mut statements = StatementCount::default();
+    statements.walk(&nodes);
+
+    let (mut depth, mut max_depth) = (0usize, 0usize);
+    let mut code_lines = vec![false; source.lines().count() + 1];
+    for lexeme in scan(source).iter().filter(|lexeme| !lexeme.is_comment()) {
+        if lexeme.is_punct('{') {
+            depth += 1;
+            max_depth = max_depth.max(depth);
+        } else if lexeme.is_punct('}') {
+            depth = depth.saturating_sub(1);
+        }
+        for line in lexeme.line..=lexeme.end_line() {
+            code_lines[line - 1] = true;
+        }
+    }
+
+    let functions = collect_function_complexity(source);
+    let scores: Vec<usize> = functions.iter().map(FunctionComplexity::score).collect();
+    ComplexitySummary {
+        total_nodes: nodes.len() + statements.0,
+        max_depth,
+        function_count: functions.len(),
+        avg_function_complexity: if scores.is_empty() {
+            0.0
+        } else {
+            scores.iter().sum::<usize>() as f64 / scores.len() as f64
+        },
+        max_function_complexity: scores.iter().copied().max().unwrap_or_default(),
+        longest_function_lines: functions
+            .iter()
+            .map(|f| f.body_len)
+            .max()
+            .unwrap_or_default(),
+        sloc: code_lines.iter().filter(|&&code| code).count(),
+    }
+}
+
+/// Summarize the file's complexity in one call for dashboards
+///
+/// Aggregates `complexity_report` with node counts, nesting and SLOC.
+#[wasm_bindgen]
+pub fn complexity_summary(source: &str) -> Result<JsValue, JsValue> {
+    to_js(&summarize_complexity(source))
+}
+
+/// One block on the path to the deepest nesting point
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NestingFrame {
+    /// Declaration keyword (`spirit`, `gen`, `fun`, ...), else the block's
+    /// leading word (`if`, `docs`, ...), else `block`
+    pub kind: String,
+    /// Declared name; empty for blocks that aren't declarations
+    pub name: String,
+    pub line: usize,
+}
+
+/// Describe the block opened by the `{` at `lexemes[open]` from its header
+///
+/// The header is the run of tokens before the brace on its line; leading
+/// `pub`, `pub(...)` and `sex` modifiers are skipped.
+fn nesting_frame(lexemes: &[Lexeme], open: usize) -> NestingFrame {
+    let line = lexemes[open].line;
+    let start = (0..open)
+        .rev()
+        .take_while(|&i| {
+            let lexeme = &lexemes[i];
+            lexeme.line == line && !lexeme.is_punct('{') && !lexeme.is_punct('}')
+        })
+        .last()
+        .unwrap_or(open);
+
+    let mut i = start;
+    while i < open {
+        let lexeme = &lexemes[i];
+        if lexeme.kind == LexKind::Word && lexeme.text == "pub" && lexemes[i + 1].is_punct('(') {
+            i = (i..open)
+                .find(|&j| lexemes[j].is_punct(')'))
+                .map_or(open, |j| j + 1);
+        } else if lexeme.kind == LexKind::Word && (lexeme.text == "pub" || lexeme.text == "sex") {
+            i += 1;
+        } else {
+            break;
+        }
+    }
+
+    let word = |j: usize| {
+        lexemes[..open]
+            .get(j)
+            .filter(|l| l.kind == LexKind::Word)
+            .map(|l| l.text)
+    };
+    let (kind, name) = match word(i) {
+        Some(kind) if kind == "spirit" || DECLARATION_KEYWORDS.contains(&kind) => {
+            (kind, word(i + 1).unwrap_or(""))
+        }
+        Some(kind) => (kind, ""),
+        None => ("block", ""),
+    };
+    NestingFrame {
+        kind: kind.to_string(),
+        name: name.to_string(),
+        line,
+    }
+}
+
+/// The chain of blocks from the outermost one to the deepest `{}` nesting
+///
+/// The first path to reach the maximum depth wins. Token-level, so it
+/// follows the same depth as `complexity_summary`'s `max_depth`.
+fn collect_deepest_nesting(source: &str) -> Vec<NestingFrame> {
+    let lexemes: Vec<Lexeme> = scan(source)
+        .into_iter()
+        .filter(|lexeme| !lexeme.is_comment())
+        .collect();
+
+    let (mut path, mut deepest) = (Vec::new(), Vec::new());
+    for (i, lexeme) in lexemes.iter().enumerate() {
+        if lexeme.is_punct('{') {
+            path.push(nesting_frame(&lexemes, i));
+            if path.len() > deepest.len() {
+                deepest = path.clone();
+            }
+        } else if lexeme.is_punct('}') {
+            path.pop();
+        }
+    }
+    deepest
+}
+
+/// Get the path of declarations and blocks leading to the deepest nesting
+///
+/// Outermost first, each with its kind, name and line. This is a
+/// token-level scan, so it works on sources that fail to parse.
+#[wasm_bindgen]
+pub fn deepest_nesting(source: &str) -> Result<JsValue, JsValue> {
+    to_js(&collect_deepest_nesting(source))
+}
+
+/// Suggested semver bump for one declaration, with the changes behind it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VersionBump {
+    /// `major`, `minor`, `patch` or `none`
+    pub bump: String,
+    pub reasons: Vec<String>,
+}
+
+/// Member names mapped to a field's type or a function's purity
+type Members<'a> = BTreeMap<&'a str, &'a str>;
+
+/// The interface of a gene, trait or system: its fields, and its public
+/// nested functions with their purity
+fn declaration_interface<'a>(
+    nodes: &'a [AstNode],
+    name: &str,
+) -> Option<(&'a AstNode, Members<'a>, Members<'a>)> {
+    let node = nodes.iter().find(|node| match node {
+        AstNode::Gene { name: n, .. }
+        | AstNode::Trait { name: n, .. }
+        | AstNode::System { name: n, .. } => n == name,
+        _ => false,
+    })?;
+    let (AstNode::Gene { statements, .. }
+    | AstNode::Trait { statements, .. }
+    | AstNode::System { statements, .. }) = node
+    else {
+        return None;
+    };
+
+    let mut fields = BTreeMap::new();
+    let mut functions = BTreeMap::new();
+    for statement in statements {
+        match statement {
+            StatementNode::HasField {
+                name, field_type, ..
+            } => {
+                fields.insert(name.as_str(), field_type.as_str());
+            }
+            StatementNode::Function {
+                name,
+                visibility,
+                purity,
+            } if visibility == "pub" => {
+                functions.insert(name.as_str(), purity.as_str());
+            }
+            _ => {}
+        }
+    }
+    Some((node, fields, functions))
+}
+
+/// Compare one declaration across two versions of the AST
+///
+/// Removed or retyped fields and removed public functions are breaking
+/// (major), as is a public function turning effectful; additions are minor,
+/// and any other change to the declaration's tokens, including to private
+/// functions, is a patch. Nested functions carry no parameter types, so a
+/// changed parameter list shows up as a patch.
+fn compare_versions(old: &[AstNode], new: &[AstNode], name: &str) -> VersionBump {
+    let (old, new) = match (
+        declaration_interface(old, name),
+        declaration_interface(new, name),
+    ) {
+        (Some(old), Some(new)) => (old, new),
+        (Some(_), None) => {
+            return VersionBump {
+                bump: "major".to_string(),
+                reasons: vec![format!("'{}' was removed", name)],
+            }
+        }
+        (None, Some(_)) => {
+            return VersionBump {
+                bump: "minor".to_string(),
+                reasons: vec![format!("'{}' was added", name)],
+            }
+        }
+        (None, None) => {
+            return VersionBump {
+                bump: "none".to_string(),
+                reasons: vec![format!("no gene, trait or system named '{}'", name)],
+            }
+        }
+    };
+    let ((old_node, old_fields, old_functions), (new_node, new_fields, new_functions)) = (old, new);
+
+    let mut major = Vec::new();
+    let mut minor = Vec::new();
+    for (field, old_type) in &old_fields {
+        match new_fields.get(field) {
+            None => major.push(format!("field '{}' was removed", field)),
+            Some(new_type) if new_type != old_type => major.push(format!(
+                "field '{}' changed type from {} to {}",
+                field, old_type, new_type
+            )),
+            Some(_) => {}
+        }
+    }
+    for field in new_fields.keys().filter(|f| !old_fields.contains_key(*f)) {
+        minor.push(format!("field '{}' was added", field));
+    }
+    for (function, old_purity) in &old_functions {
+        match new_functions.get(function) {
+            None => major.push(format!("function '{}' was removed", function)),
+            Some(&"sex") if *old_purity != "sex" => {
+                major.push(format!("function '{}' became effectful", function))
+            }
+            Some(_) => {}
+        }
+    }
+    for function in new_functions
+        .keys()
+        .filter(|f| !old_functions.contains_key(*f))
+    {
+        minor.push(format!("function '{}' was added", function));
+    }
+
+    let checksum = |node: &AstNode| match node {
+        AstNode::Gene { checksum, .. }
+        | AstNode::Trait { checksum, .. }
+        | AstNode::System { checksum, .. } => checksum.clone(),
+        _ => String::new(),
+    };
+    let (bump, reasons) = if !major.is_empty() {
+        ("major", major.into_iter().chain(minor).collect())
+    } else if !minor.is_empty() {
+        ("minor", minor)
+    } else if checksum(old_node) != checksum(new_node) {
+        (
+            "patch",
+            vec![format!("'{}' changed without interface changes", name)],
+        )
+    } else {
+        ("none", vec![])
+    };
+    VersionBump {
+        bump: bump.to_string(),
+        reasons,
+    }
+}
+
+/// Suggest a semver bump for a gene, trait or system between two sources
+///
+/// Returns `none` with a reason if either source fails to parse.
+#[wasm_bindgen]
+pub fn suggest_version_bump(
+    old_source: &str,
+    new_source: &str,
+    name: &str,
+) -> Result<JsValue, JsValue> {
+    to_js(&suggest_bump(old_source, new_source, name))
+}
+
+/// Parse both sources and compare the named declaration
+fn suggest_bump(old_source: &str, new_source: &str, name: &str) -> VersionBump {
+    match (parse_nodes(old_source), parse_nodes(new_source)) {
+        (Ok(mut old), Ok(mut new)) => {
+            assign_checksums(old_source, &mut old);
+            assign_checksums(new_source, &mut new);
+            compare_versions(&old, &new, name)
+        }
+        (old, _) => VersionBump {
+            bump: "none".to_string(),
+            reasons: vec![format!(
+                "{} source does not parse",
+                if old.is_err() { "old" } else { "new" }
+            )],
+        },
+    }
+}
+
+/// Lexer state at the start of a source line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LineState {
+    Normal,
+    InBlockComment,
+    InString,
+}
+
+/// Compute the lexer state at the start of every line
+fn compute_line_states(source: &str) -> Vec<LineState> {
+    let mut states = vec![LineState::Normal; source.split('\n').count()];
+    for lexeme in scan(source) {
+        let state = match lexeme.kind {
+            LexKind::BlockComment => LineState::InBlockComment,
+            LexKind::Str => LineState::InString,
+            _ => continue,
+        };
+        // Lines after the first one the lexeme touches start inside it
+        for line in lexeme.line + 1..=lexeme.end_line() {
+            states[line - 1] = state;
+        }
+    }
+    states
+}
+
+/// Report the lexer state at the start of each line (index 0 is line 1)
+///
+/// Lets line-based highlighters resume correctly after an edit without
+/// re-lexing the whole document.
+#[wasm_bindgen]
+pub fn line_states(source: &str) -> Result<JsValue, JsValue> {
+    to_js(&compute_line_states(source))
+}
+
+/// Zero-based position in LSP coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LspPosition {
+    pub line: usize,
+    pub character: usize,
+}
+
+/// Half-open range in LSP coordinates
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct LspRange {
+    pub start: LspPosition,
+    pub end: LspPosition,
+}
+
+/// Diagnostic in the Language Server Protocol shape
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LspDiagnostic {
+    pub range: LspRange,
+    /// 1 = error, 2 = warning
+    pub severity: u8,
+    pub code: String,
+    pub source: String,
+    pub message: String,
+}
+
+/// A document sent by an LSP client
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TextDocument {
+    pub uri: String,
+    pub text: String,
+}
+
+/// Compile a document and convert its errors and warnings to LSP diagnostics
+///
+/// Warnings cover their whole line, since the checks only track lines.
+fn lsp_diagnostics(source: &str) -> Vec<LspDiagnostic> {
+    let (errors, warnings) = match parse_nodes(source) {
+        Ok(ast) => (check_errors(source, &ast), check_warnings(source, &ast)),
+        Err(err) => (vec![convert_parse_error(source, &err)], vec![]),
+    };
+    // LSP positions are 0-based
+    let position = |line: usize, column: usize| LspPosition {
+        line: line.saturating_sub(1),
+        character: column.saturating_sub(1),
+    };
+    let line_len = |line: usize| {
+        source
+            .lines()
+            .nth(line.saturating_sub(1))
+            .map_or(0, |text| text.chars().count())
+    };
+
+    let errors = errors.into_iter().map(|error| LspDiagnostic {
+        range: LspRange {
+            start: position(error.line, error.column),
+            end: position(error.end_line, error.end_column),
+        },
+        severity: 1,
+        code: error.error_type,
+        source: "dol".to_string(),
+        message: error.message,
+    });
+    let warnings = warnings.into_iter().map(|warning| LspDiagnostic {
+        range: LspRange {
+            start: position(warning.line, 1),
+            end: position(warning.line, line_len(warning.line) + 1),
+        },
+        severity: 2,
+        code: warning.kind.to_string(),
+        source: "dol".to_string(),
+        message: warning.message,
+    });
+    errors.chain(warnings).collect()
+}
+
+/// Get LSP-shaped diagnostics for a single document
+#[wasm_bindgen]
+pub fn diagnostics_lsp(source: &str) -> Result<JsValue, JsValue> {
+    to_js(&lsp_diagnostics(source))
+}
+
+/// Get LSP-shaped diagnostics for a batch of documents, keyed by URI
+///
+/// `documents` is an array of `{ uri, text }` objects.
+#[wasm_bindgen]
+pub fn diagnostics_batch(documents: JsValue) -> Result<JsValue, JsValue> {
+    let documents: Vec<TextDocument> = serde_wasm_bindgen::from_value(documents)
+        .map_err(|e| JsValue::from_str(&format!("Invalid documents: {}", e)))?;
+    to_js(&batch_diagnostics(&documents))
+}
+
+/// Run `lsp_diagnostics` over each document
+fn batch_diagnostics(documents: &[TextDocument]) -> BTreeMap<String, Vec<LspDiagnostic>> {
+    documents
+        .iter()
+        .map(|doc| (doc.uri.clone(), lsp_diagnostics(&doc.text)))
+        .collect()
+}
+
+/// Render declarations as a box-drawing tree, one line per node
+fn render_tree(nodes: &[AstNode]) -> String {
+    let mut lines = Vec::new();
+    for (i, node) in nodes.iter().enumerate() {
+        let last = i + 1 == nodes.len();
+        lines.push(format!(
+            "{}{}",
+            if last { "└─ " } else { "├─ " },
+            declaration_signature(node)
+        ));
+
+        let children = child_labels(node);
+        let indent = if last { "   " } else { "│  " };
+        for (j, child) in children.iter().enumerate() {
+            let connector = if j + 1 == children.len() {
+                "└─ "
+            } else {
+                "├─ "
+            };
+            lines.push(format!("{}{}{}", indent, connector, child));
+        }
+    }
+    lines.join("\n")
+}
+
+/// Render the AST as an indented plain-text tree for terminal inspection
+///
+/// Returns an empty string if the source fails to parse.
+#[wasm_bindgen]
+pub fn tree_view(source: &str) -> String {
+    render_tree(&parse_nodes(source).unwrap_or_default())
+}
+
+/// A named source file supplied by a multi-file caller
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceFile {
+    pub path: String,
+    pub source: String,
+}
+
+/// One place a system version is declared
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VersionEntry {
+    pub version: String,
+    pub path: String,
+    pub line: usize,
+}
+
+/// Group every system version declared across `files` by system name
+///
+/// Files that fail to parse contribute no entries.
+fn collect_system_versions(files: &[SourceFile]) -> BTreeMap<String, Vec<VersionEntry>> {
+    let mut versions: BTreeMap<String, Vec<VersionEntry>> = BTreeMap::new();
+    for file in files {
+        for node in parse_nodes(&file.source).unwrap_or_default() {
+            if let AstNode::System {
+                name,
+                version,
+                line,
+                ..
+            } = node
+            {
+                versions.entry(name).or_default().push(VersionEntry {
+                    version,
+                    path: file.path.clone(),
+                    line,
+                });
+            }
+        }
+    }
+    versions
+}
+
+/// Map each system name to every version declared for it across a project
+///
+/// `sources` is an array of `{ path, source }` objects. The result is an
+/// object keyed by system name, useful for spotting version drift and
+/// duplicate versions between modules.
+#[wasm_bindgen]
+pub fn project_system_versions(sources: JsValue) -> Result<JsValue, JsValue> {
+    let files: Vec<SourceFile> = serde_wasm_bindgen::from_value(sources)
+        .map_err(|e| JsValue::from_str(&format!("Invalid sources: {}", e)))?;
+    to_js(&collect_system_versions(&files))
+}
+
+/// Serialize a result struct into a JavaScript value
+///
+/// Maps are emitted as plain objects so keyed results read naturally in JS.
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    let serializer = serde_wasm_bindgen::Serializer::new().serialize_maps_as_objects(true);
+    value
+        .serialize(&serializer)
+        .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+}
+
+/// Result from compile_to_wasm containing bytecode or error
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WasmBytecodeResult {
+    /// Whether compilation was successful
+    pub success: bool,
+    /// WASM bytecode (only present on success)
+    pub bytecode: Option<Vec<u8>>,
+    /// Error message (only present on failure)
+    pub error: Option<String>,
+    /// Size of generated bytecode in bytes
+    pub bytecode_size: usize,
+}
+
+/// Compile DOL source code to WebAssembly bytecode
+///
+/// This function parses DOL source, then uses the WasmCompiler to emit
+/// actual WASM bytecode that can be instantiated via the browser's WebAssembly API.
+///
+/// # Example (JavaScript)
+///
+/// ```javascript
+/// const result = compile_to_wasm(dolSource);
+/// if (result.success) {
+///     const wasmModule = await WebAssembly.compile(result.bytecode);
+///     const instance = await WebAssembly.instantiate(wasmModule);
+///     // Call exported functions...
+/// }
+/// ```
+#[wasm_bindgen]
+pub fn compile_to_wasm(source: &str) -> Result<JsValue, JsValue> {
+    // Parse the DOL source
+    match parse_file_all(source) {
+        Ok(declarations) => {
+            if declarations.is_empty() {
+                let result = WasmBytecodeResult {
+                    success: false,
+                    bytecode: None,
+                    error: Some("No declarations found in source".to_string()),
+                    bytecode_size: 0,
+                };
+                return serde_wasm_bindgen::to_value(&result)
+                    .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)));
+            }
+
+            // Filter to only function declarations (WASM compiler only supports functions)
+            let has_functions = declarations
+                .iter()
+                .any(|d| matches!(d, Declaration::Function(_)));
+
+            if !has_functions {
+                let result = WasmBytecodeResult {
+                    success: false,
+                    bytecode: None,
+                    error: Some("No function declarations found. WASM compilation requires at least one 'fun' declaration.".to_string()),
+                    bytecode_size: 0,
+                };
+                return serde_wasm_bindgen::to_value(&result)
+                    .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)));
+            }
+
+            // Create a DolFile from the declarations to support multiple functions
+            let dol_file = DolFile {
+                module: None,
+                uses: Vec::new(),
+                declarations: declarations.clone(),
+            };
+
+            // Create WASM compiler with optimization enabled
+            let mut compiler = WasmCompiler::new().with_optimization(true);
+
+            // Compile ALL functions into a single WASM module
+            match compiler.compile_file(&dol_file) {
+                Ok(bytecode) => {
+                    let bytecode_size = bytecode.len();
+                    let result = WasmBytecodeResult {
+                        success: true,
+                        bytecode: Some(bytecode),
+                        error: None,
+                        bytecode_size,
+                    };
+                    serde_wasm_bindgen::to_value(&result)
+                        .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+                }
+                Err(err) => {
+                    let result = WasmBytecodeResult {
+                        success: false,
+                        bytecode: None,
+                        error: Some(format!("WASM compilation error: {}", err)),
+                        bytecode_size: 0,
+                    };
+                    serde_wasm_bindgen::to_value(&result)
+                        .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+                }
+            }
+        }
+        Err(err) => {
+            let result = WasmBytecodeResult {
+                success: false,
+                bytecode: None,
+                error: Some(format!("Parse error: {}", err)),
+                bytecode_size: 0,
+            };
+            serde_wasm_bindgen::to_value(&result)
+                .map_err(|e| JsValue::from_str(&format!("Serialization error: {}", e)))
+        }
+    }
+}
+
+/// Compile DOL source to WASM bytecode and return as Uint8Array
 ///
 /// This is a convenience function that returns the raw bytecode directly
 /// as a JavaScript Uint8Array, suitable for immediate use with WebAssembly.instantiate().
@@ -702,32 +3921,785 @@ pub fn compile_to_wasm_bytes(source: &str) -> Result<Vec<u8>, JsValue> {
     let declarations = parse_file_all(source)
         .map_err(|err| JsValue::from_str(&format!("Parse error: {}", err)))?;
 
-    if declarations.is_empty() {
-        return Err(JsValue::from_str("No declarations found in source"));
+    if declarations.is_empty() {
+        return Err(JsValue::from_str("No declarations found in source"));
+    }
+
+    // Create a DolFile from the declarations to support multiple functions
+    let dol_file = DolFile {
+        module: None,
+        uses: Vec::new(),
+        declarations,
+    };
+
+    // Create WASM compiler with optimization enabled
+    let mut compiler = WasmCompiler::new().with_optimization(true);
+
+    // Compile ALL functions into a single WASM module
+    compiler
+        .compile_file(&dol_file)
+        .map_err(|err| JsValue::from_str(&format!("WASM compilation error: {}", err)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_simple_gene() {
+        let source = r#"
+gen Counter {
+    counter has value
+}
+
+docs {
+    A simple counter gene.
+}
+        "#;
+        assert!(validate_dol(source));
+    }
+
+    #[test]
+    fn test_validate_system_syntax() {
+        let source = r#"
+system Counter @ 0.1.0 {
+    requires base >= 0.0.1
+    all counters is tracked
+}
+
+docs {
+    A counter system.
+}
+        "#;
+        assert!(validate_dol(source));
+    }
+
+    #[test]
+    fn test_invalid_syntax() {
+        let source = r#"
+gen Unclosed {
+    has value
+        "#;
+        assert!(!validate_dol(source));
+    }
+
+    #[test]
+    fn test_validate_matches_compile() {
+        let samples = [
+            "gen Counter {\n    counter has value\n}\n\ndocs {\n    A counter.\n}\n",
+            "gen Unclosed {\n    has value\n",
+            "fun add(a: i64, b: i64) -> i64 {\n    return a + b\n}\n",
+            "gen First {\n    first has value\n}\n\ndocs {\n    Ok.\n}\n\ngen {\n",
+            "",
+        ];
+        for source in samples {
+            let valid = validate_dol(source);
+            assert_eq!(valid, compile(source).success, "{:?}", source);
+        }
+    }
+
+    #[test]
+    fn test_format_dol_leaves_invalid_source() {
+        let source = "gen Unclosed {\n  has value\n";
+        assert_eq!(format_dol(source), source);
+    }
+
+    /// Counts functions anywhere in the tree, and the effectful ones
+    #[derive(Default)]
+    struct FunctionCounter {
+        total: usize,
+        effectful: usize,
+    }
+
+    impl AstVisitor for FunctionCounter {
+        fn visit_function(&mut self, node: &AstNode) {
+            if let AstNode::Function { name, purity, .. } = node {
+                self.visit_nested_function(name, purity);
+            }
+        }
+
+        fn visit_nested_function(&mut self, _name: &str, purity: &str) {
+            self.total += 1;
+            if purity == "sex" {
+                self.effectful += 1;
+            }
+        }
+    }
+
+    #[test]
+    fn test_ast_visitor_counts_functions() {
+        let source = r#"
+fun read() -> i64 {
+    return 1
+}
+
+sex fun write() -> i64 {
+    return 2
+}
+
+gen Counter {
+    has value: i64
+
+    fun get() -> i64 {
+        return value
+    }
+
+    sex fun increment() -> i64 {
+        return value + 1
+    }
+}
+
+docs {
+    A counter.
+}
+"#;
+        let nodes = parse_nodes(source).unwrap();
+        let mut counter = FunctionCounter::default();
+        counter.walk(&nodes);
+        assert_eq!(counter.total, 4);
+        assert_eq!(counter.effectful, 2);
+    }
+
+    #[test]
+    fn test_duplicate_constraint_logic() {
+        let source = r#"constraint positive {
+    count never overflows
+}
+
+docs {
+    Counts stay in range.
+}
+
+constraint bounded {
+    total never overflows
+}
+
+docs {
+    Totals stay in range.
+}
+
+constraint nonnegative {
+    count never overflows
+}
+
+docs {
+    Counts stay in range, again.
+}
+"#;
+        let result = compile(source);
+        assert!(result.success);
+        let duplicates: Vec<&String> = result
+            .warnings
+            .iter()
+            .filter(|w| w.starts_with("DuplicateConstraintLogic:"))
+            .collect();
+        assert_eq!(
+            duplicates,
+            vec![
+                "DuplicateConstraintLogic: constraints 'positive' at line 1, \
+                 'nonnegative' at line 17 have identical bodies; merge them or keep one"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_error_end_range() {
+        let range =
+            |error: &CompileError| (error.line, error.column, error.end_line, error.end_column);
+
+        // A stray closing brace spans just that character
+        let result = compile("gen Counter {\n    has value: i64\n}\n}\n");
+        assert!(!result.success);
+        assert_eq!(range(&result.errors[0]), (4, 1, 4, 2));
+
+        // A misplaced literal spans the whole token
+        let result = compile("gen Counter {\n    has 1024: i64\n}\n");
+        assert!(!result.success);
+        assert_eq!(range(&result.errors[0]), (2, 9, 2, 13));
+    }
+
+    #[test]
+    fn test_parse_matches_compile() {
+        let samples = [
+            "gen Counter {\n    counter has value\n}\n\ndocs {\n    A counter.\n}\n",
+            "gen Unclosed {\n    has value\n",
+        ];
+        for source in samples {
+            let parsed = parse(source);
+            let compiled = compile(source);
+            assert_eq!(parsed.success, compiled.success);
+            assert_eq!(parsed.ast, compiled.ast);
+            assert_eq!(parsed.errors, compiled.errors);
+            assert!(parsed.warnings.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_duplicate_fields() {
+        let source = r#"gen Counter {
+    has count: i32
+    has total: i32
+    has count: i64
+}
+
+docs {
+    A counter.
+}
+"#;
+        let result = compile(source);
+        assert!(!result.success);
+        assert_eq!(result.errors.len(), 1);
+        let error = &result.errors[0];
+        assert_eq!(error.error_type, "DuplicateField");
+        assert_eq!((error.line, error.column), (4, 9));
+        assert_eq!(error.end_column, 14);
+    }
+
+    #[test]
+    fn test_keyword_misuse() {
+        let source =
+            "gen Router {\n    has match: i32\n    has `loop`: i32\n    has route: i32\n}\n";
+        let misuse = collect_keyword_misuse(source);
+        assert_eq!(misuse.len(), 1);
+        assert_eq!(misuse[0].keyword, "match");
+        assert_eq!((misuse[0].line, misuse[0].column), (2, 9));
+    }
+
+    #[test]
+    fn test_truncate_ast() {
+        let source = r#"gen Counter {
+    has value: i64
+
+    fun tick() -> i64 {
+        return this.value + 1
+    }
+}
+
+docs {
+    A counter.
+}
+"#;
+        let depth = |max_output_depth| {
+            let options = CompileOptions {
+                max_output_depth,
+                ..CompileOptions::default()
+            };
+            compile_with_options(source, &options).ast
+        };
+
+        let full = depth(None);
+        assert!(matches!(&full[0], AstNode::Gene { statements, .. } if statements.len() == 2));
+        assert_eq!(depth(Some(2)), full);
+
+        match &depth(Some(1))[0] {
+            AstNode::Gene { statements, .. } => {
+                assert_eq!(statements, &vec![StatementNode::Truncated { omitted: 2 }]);
+            }
+            other => panic!("expected a gene, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_invalid_versions() {
+        let invalid = |version: &str| {
+            let source = format!(
+                "system Clock @ {} {{\n    requires base >= 0.0.1\n}}\n\ndocs {{\n}}\n",
+                version
+            );
+            compile(&source)
+                .errors
+                .into_iter()
+                .filter(|e| e.error_type == "InvalidVersion")
+                .collect::<Vec<_>>()
+        };
+
+        assert!(invalid("1.0.0").is_empty());
+        let errors = invalid("1.2");
+        assert_eq!(errors.len(), 1);
+        assert_eq!((errors[0].line, errors[0].column), (1, 16));
+        assert_eq!(errors[0].end_column, 19);
+        let errors = invalid("1..0");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].message.contains("'1..0'"));
+    }
+
+    #[test]
+    fn test_checksums_track_declaration_edits() {
+        let function = |name: &str, line: usize| AstNode::Function {
+            name: name.to_string(),
+            visibility: "private".to_string(),
+            purity: "pure".to_string(),
+            params: vec![],
+            return_type: None,
+            line,
+            checksum: String::new(),
+        };
+        let checksums = |source: &str| {
+            let mut nodes = vec![function("first", 1), function("second", 5)];
+            assign_checksums(source, &mut nodes);
+            nodes
+                .into_iter()
+                .map(|node| match node {
+                    AstNode::Function { checksum, .. } => checksum,
+                    _ => unreachable!(),
+                })
+                .collect::<Vec<_>>()
+        };
+
+        let second = "\n\nfun second() {\n    return 2\n}\n";
+        let original = checksums(&format!("fun first() {{\n    return 1\n}}{}", second));
+        let edited = checksums(&format!("fun first() {{\n    return 10\n}}{}", second));
+        let commented = checksums(&format!("fun first() {{\n  return 1 // one\n}}{}", second));
+        assert_ne!(original[0], edited[0]);
+        assert_eq!(original[1], edited[1]);
+        assert_eq!(original, commented);
+    }
+
+    #[test]
+    fn test_bracket_balance() {
+        let balance = compute_bracket_balance("gen A {\n    fun f(x: i32) {\n        // }\n");
+        assert_eq!(balance.braces, 2);
+        assert_eq!(balance.parens, 0);
+        assert!(balance.first_unmatched.is_none());
+
+        let balance = compute_bracket_balance("gen A {\n    has xs: [i32]]\n}\n");
+        assert_eq!(balance.braces, 0);
+        let unmatched = balance.first_unmatched.unwrap();
+        assert_eq!(unmatched.delimiter, "]");
+        assert_eq!((unmatched.line, unmatched.column), (2, 18));
+    }
+
+    #[test]
+    fn test_canonical_case() {
+        let source = "fun addItem() -> i64 {\n  // addItem keeps its comment\n  return 1\n}\n\n\
+                      fun main() -> i64 {\n  return addItem()\n}\n";
+        let result = canonical_case(source);
+        assert_eq!(
+            result.renames,
+            vec![CaseRename {
+                from: "addItem".to_string(),
+                to: "add_item".to_string(),
+            }]
+        );
+        assert!(result
+            .formatted
+            .starts_with("fun add_item() -> i64 {\n    // addItem keeps its comment\n"));
+        assert!(result.formatted.contains("    return add_item()\n"));
+
+        // Renaming `itemCount` would collide with `item_count`
+        let source = "gen Cart {\n    has itemCount: i64\n    has item_count: i64\n}\n\n\
+                      docs {\n    A cart.\n}\n";
+        let result = canonical_case(source);
+        assert_eq!(result.formatted, source);
+        assert!(result.renames.is_empty());
+    }
+
+    #[test]
+    fn test_recompile_reuses_untouched_declarations() {
+        let gene = |name: &str, body: &str| {
+            format!(
+                "gen {} {{\n    {}\n}}\n\ndocs {{\n    {}.\n}}\n",
+                name, body, name
+            )
+        };
+        let before = format!(
+            "{}\n{}",
+            gene("Counter", "counter has value"),
+            gene("Timer", "timer has ticks")
+        );
+        let after = format!(
+            "{}\n{}",
+            gene("Counter", "counter has value"),
+            gene("Timer", "timer has ticks\n    timer has limit")
+        );
+        let previous = compile(&before);
+        assert!(previous.success);
+
+        // The new statement is on line 11 of the edited source
+        let result = recompile(&after, Some(previous.clone()), 11, 11);
+        assert_eq!(result, compile(&after));
+        assert_eq!(result.ast[0], previous.ast[0]);
+        assert_ne!(result.ast[1], previous.ast[1]);
+
+        // Lines that don't match the source fall back to a full compile
+        let mut stale = previous;
+        for node in &mut stale.ast {
+            *node_line_mut(node) = 0;
+        }
+        assert_eq!(recompile(&after, Some(stale), 11, 11), compile(&after));
+    }
+
+    #[test]
+    fn test_count_nodes_recurses_into_bodies() {
+        let source = r#"system Clock @ 1.0.0 {
+    has ticks: u64
+
+    fun tick() -> u64 {
+        return this.ticks + 1
+    }
+
+    fun reset() -> u64 {
+        return 0
+    }
+}
+
+docs {
+    A clock.
+}
+"#;
+        let result = compile(source);
+        assert!(result.success);
+        assert_eq!(result.metadata.system_count, 1);
+        assert_eq!(result.metadata.function_count, 2);
+        assert_eq!(result.metadata.field_count, 1);
+    }
+
+    #[test]
+    fn test_strict_promotes_warnings() {
+        let strict = |source: &str| {
+            let (result, warnings) = check_source(source);
+            promote_warnings(result, &warnings)
+        };
+        let source = "gen Counter {\n    counter has value\n}\n\ndocs {\n    A counter.\n}\n";
+        let clean = compile(source);
+        assert_eq!(strict(source), clean);
+
+        let source = "gen String {\n    has value: i64\n}\n\ndocs {\n    A shadowing gene.\n}\n";
+        let normal = compile(source);
+        assert!(normal.success);
+        assert_eq!(normal.warnings.len(), 1);
+        let strict = strict(source);
+        assert!(!strict.success);
+        assert_eq!(strict.errors.len(), 1);
+        let error = &strict.errors[0];
+        assert_eq!(error.error_type, "StrictWarning");
+        assert_eq!(error.message, normal.warnings[0]);
+        assert_eq!((error.line, error.column), (1, 1));
+        assert_eq!((error.end_line, error.end_column), (1, 2));
+    }
+
+    #[test]
+    fn test_strictness_levels() {
+        let source = "const LIMIT = 100\n";
+        let at = |level| compile_with_options(source, &CompileOptions::from_level(level));
+
+        let lenient = at(StrictnessLevel::Lenient);
+        assert!(lenient.success);
+        assert_eq!(lenient, compile(source));
+        assert!(at(StrictnessLevel::Standard).success);
+
+        let strict = at(StrictnessLevel::Strict);
+        assert!(!strict.success);
+        assert_eq!(
+            strict.warnings,
+            vec!["MissingType: constant 'LIMIT' at line 1 has no type annotation"]
+        );
+        assert_eq!(strict.errors.len(), 1);
+        assert_eq!(strict.errors[0].error_type, "StrictWarning");
+        assert_eq!(strict.errors[0].message, strict.warnings[0]);
+    }
+
+    #[test]
+    fn test_complexity_report_orders_largest_first() {
+        let source = r#"
+fun small(a: i64) -> i64 {
+    return a
+}
+
+gen Counter {
+    fun step(by: Map<String, i64>, times: i64) {
+        if times > 0 {
+            for x in by {
+                total = total + x
+            }
+        }
+    }
+}
+"#;
+        let report = collect_function_complexity(source);
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].name, "step");
+        assert_eq!(report[0].enclosing.as_deref(), Some("Counter"));
+        assert_eq!(report[0].param_count, 2);
+        assert_eq!(report[0].nesting_depth, 2);
+        assert_eq!(report[0].body_len, 7);
+        assert_eq!(report[1].name, "small");
+        assert_eq!(report[1].enclosing, None);
+        assert_eq!((report[1].param_count, report[1].body_len), (1, 3));
+    }
+
+    #[test]
+    fn test_deepest_nesting() {
+        let source = r#"gen Flat {
+    has x: i64
+}
+
+pub spirit Physics {
+    gen Body {
+        pub sex fun step(dt: f64) -> f64 {
+            return dt
+        }
+    }
+}
+"#;
+        let frame = |kind: &str, name: &str, line: usize| NestingFrame {
+            kind: kind.to_string(),
+            name: name.to_string(),
+            line,
+        };
+        assert_eq!(
+            collect_deepest_nesting(source),
+            vec![
+                frame("spirit", "Physics", 5),
+                frame("gen", "Body", 6),
+                frame("fun", "step", 7),
+            ]
+        );
+
+        let source = "fun check(x: i64) -> i64 {\n    if x > 0 {\n        return x\n    }\n}\n";
+        assert_eq!(
+            collect_deepest_nesting(source),
+            vec![frame("fun", "check", 1), frame("if", "", 2)]
+        );
+    }
+
+    #[test]
+    fn test_complexity_summary() {
+        let source = r#"
+// Adds two numbers
+fun add(a: i64, b: i64) -> i64 {
+    return a + b
+}
+
+gen Counter {
+    has count: i64
+
+    fun step(times: i64) {
+        if times > 0 {
+            count = count + times
+        }
+    }
+}
+
+docs {
+    A counter.
+}
+"#;
+        let summary = summarize_complexity(source);
+        let report = collect_function_complexity(source);
+        assert_eq!(summary.function_count, report.len());
+        assert_eq!(summary.max_function_complexity, report[0].score());
+        assert_eq!(summary.longest_function_lines, 5);
+        assert_eq!(summary.max_depth, 3);
+        assert_eq!(summary.sloc, 14);
+        assert_eq!(summary.total_nodes, 4);
+        let total: usize = report.iter().map(FunctionComplexity::score).sum();
+        assert_eq!(summary.avg_function_complexity, total as f64 / 2.0);
+    }
+
+    #[test]
+    fn test_chained_comparisons() {
+        let source = r#"
+constraint bounded {
+    self.x < y < z
+    self.low <= self.x
+    (self.a < b) == (c > d)
+    self.x > 0 && self.x < 10
+}
+
+gen Meter {
+    has level: i64
+
+    rule in_range {
+        0 <= this.level <= 100
+    }
+}
+
+fun f(x: i64) -> i64 {
+    return a < b < c
+}
+"#;
+        let warnings: Vec<String> = check_chained_comparisons(source)
+            .iter()
+            .map(Warning::to_string)
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![
+                "ChainedComparison: chained comparison is ambiguous at line 3",
+                "ChainedComparison: chained comparison is ambiguous at line 13",
+            ]
+        );
+
+        let generic = r#"
+gen Schedule {
+    has slots: List<Option<i64>>
+
+    rule bounded {
+        let limits: Map<string, List<i64>> = this.limits
+        this.count > 0
+    }
+}
+"#;
+        assert!(check_chained_comparisons(generic).is_empty());
     }
 
-    // Create a DolFile from the declarations to support multiple functions
-    let dol_file = DolFile {
-        module: None,
-        uses: Vec::new(),
-        declarations,
-    };
+    #[test]
+    fn test_metadata_matches_compile() {
+        let samples = [
+            "gen Counter {\n    has value: i32\n}\n\ndocs {\n    A counter.\n}\n",
+            "fun add(a: i64, b: i64) -> i64 {\n    return a + b\n}\n",
+            "gen Unclosed {\n    has value\n",
+        ];
+        for source in samples {
+            assert_eq!(metadata(source), compile(source).metadata, "{:?}", source);
+        }
+        assert_eq!(metadata(samples[1]).function_count, 1);
+    }
 
-    // Create WASM compiler with optimization enabled
-    let mut compiler = WasmCompiler::new().with_optimization(true);
+    #[test]
+    fn test_unreachable_after_return() {
+        let source = r#"
+fun f() { return 1
+    x = 2 }
 
-    // Compile ALL functions into a single WASM module
-    compiler
-        .compile_file(&dol_file)
-        .map_err(|err| JsValue::from_str(&format!("WASM compilation error: {}", err)))
+fun g(a: i64) -> i64 {
+    if a > 0 {
+        return a
+    }
+    return a +
+        1
 }
+"#;
+        let warnings = check_unreachable_after_return(source);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, "UnreachableCode");
+        assert_eq!(warnings[0].line, 3);
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[test]
+    fn test_unreachable_after_return_continuations() {
+        let source = r#"
+fun sum(a: i64, b: i64, c: i64) -> i64 {
+    return a +
+        b + c
+}
+
+fun call(a: i64, b: i64) -> i64 {
+    return add(
+        a,
+        b)
+}
+
+fun pick(s: Status) -> i64 {
+    match s {
+        Active => return 1,
+        Idle => 2,
+        _ => return 0
+    }
+}
+"#;
+        assert!(check_unreachable_after_return(source).is_empty());
+    }
 
     #[test]
-    fn test_validate_simple_gene() {
+    fn test_match_exhaustiveness() {
+        let source = r#"
+fun name(s: Status) -> string {
+    match s {
+        Active => "active",
+        Idle => match (s) { _ => "idle" },
+    }
+}
+
+fun code(s: Status) -> i64 {
+    match s {
+        Active => 1,
+        _ if s == s => 2,
+        _ => 0,
+    }
+}
+"#;
+        let warnings = check_match_exhaustiveness(source);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, "NonExhaustiveMatch");
+        assert_eq!(warnings[0].line, 3);
+    }
+
+    #[test]
+    fn test_suggest_version_bump() {
+        let gene = |members: &str| {
+            format!(
+                "gen Clock {{\n    has ticks: i64\n{}}}\n\ndocs {{\n    A clock.\n}}\n",
+                members
+            )
+        };
+        let tick = "    fun tick() -> i64 {\n        return this.ticks + 1\n    }\n";
+        let reset = "    pub fun reset() -> i64 {\n        return 0\n    }\n";
+        let old = gene(&format!("{}{}", tick, reset));
+
+        let removed = suggest_bump(&old, &gene(tick), "Clock");
+        assert_eq!(removed.bump, "major");
+        assert_eq!(removed.reasons, vec!["function 'reset' was removed"]);
+        assert_eq!(suggest_bump(&old, &gene(reset), "Clock").bump, "patch");
+
+        let effectful = reset.replace("pub fun", "pub sex fun");
+        let effectful = gene(&format!("{}{}", tick, effectful));
+        assert_eq!(suggest_bump(&old, &effectful, "Clock").bump, "major");
+
+        let added = gene(&format!("{}{}    has paused: bool\n", tick, reset));
+        assert_eq!(suggest_bump(&old, &added, "Clock").bump, "minor");
+
+        let edited = gene(&format!("{}{}", tick.replace("+ 1", "+ 2"), reset));
+        assert_eq!(suggest_bump(&old, &edited, "Clock").bump, "patch");
+        assert_eq!(suggest_bump(&old, &old, "Clock").bump, "none");
+    }
+
+    #[test]
+    fn test_version() {
+        let version = get_version();
+        assert!(!version.is_empty());
+    }
+
+    #[test]
+    fn test_compile_dol_to_json_round_trip() {
+        let source = "fun add(a: i64, b: i64) -> i64 {\n    return a + b\n}\n";
+        let json = compile_dol_to_json(source);
+        let parsed: CompileResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, compile(source));
+    }
+
+    #[test]
+    fn test_compile_dol_pretty_round_trip() {
+        let source = "gen Counter {\n    counter has value\n}\n\ndocs {\n    A counter.\n}\n";
+        let json = compile_dol_pretty(source);
+        assert!(json.contains("\n  \"ast\": [\n"));
+        assert!(json.contains("\n      \"statements\": [\n"));
+        let parsed: CompileResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, compile(source));
+    }
+
+    #[test]
+    fn test_ast_json_valid() {
+        let json =
+            r#"[{"type":"Const","name":"LIMIT","visibility":"pub","const_type":null,"line":1}]"#;
+        let result = check_ast_json(json);
+        assert!(result.valid);
+        assert!(result.error.is_none());
+    }
+
+    #[test]
+    fn test_ast_json_missing_type_tag() {
+        let json = r#"[{"name":"Counter","visibility":"pub","line":1}]"#;
+        let result = check_ast_json(json);
+        assert!(!result.valid);
+        let error = result.error.unwrap();
+        assert!(error.starts_with("[0]"), "{}", error);
+        assert!(error.contains("`type`"), "{}", error);
+    }
+
+    #[test]
+    fn test_list_constraints_fields() {
         let source = r#"
 gen Counter {
     counter has value
@@ -736,37 +4708,584 @@ gen Counter {
 docs {
     A simple counter gene.
 }
+
+constraint positive {
+    value never negative
+}
+
+docs {
+    Counter values stay positive.
+}
         "#;
-        assert!(validate_dol(source));
+        let nodes = parse_nodes(source).unwrap();
+        let constraints = collect_constraints(&nodes);
+        assert_eq!(constraints.len(), 1);
+        assert_eq!(constraints[0].name, "positive");
+        assert_eq!(constraints[0].fields, vec!["value".to_string()]);
+        assert_eq!(constraints[0].parent.as_deref(), Some("Counter"));
     }
 
     #[test]
-    fn test_validate_system_syntax() {
+    fn test_referenced_fields_strip_receiver() {
+        let never = |subject: &str| StatementNode::Never {
+            subject: subject.to_string(),
+            action: "negative".to_string(),
+        };
+        let statements = [never("self.value"), never("this.value"), never("limit")];
+        assert_eq!(referenced_fields(&statements), vec!["value", "limit"]);
+    }
+
+    #[test]
+    fn test_error_context_surrounding_lines() {
+        let source = "gen Broken {\n    counter has value\n    has ???\n}\n";
+        let error = CompileError {
+            message: "unexpected token".to_string(),
+            line: 3,
+            column: 9,
+            end_line: 3,
+            end_column: 10,
+            error_type: "ParseError".to_string(),
+        };
+        let context = build_error_context(source, &error, 1);
+        assert_eq!(context.start_line, 2);
+        assert_eq!(context.caret_line, 3);
+        assert_eq!(context.caret_column, 9);
+        assert_eq!(context.snippet, "    counter has value\n    has ???\n}");
+    }
+
+    #[test]
+    fn test_error_context_from_compile() {
+        let source = "gen Unclosed {\n    has value\n";
+        let result = compile(source);
+        assert!(!result.success);
+        let context = build_error_context(source, &result.errors[0], 1);
+        assert!(context.start_line <= context.caret_line);
+        assert!(!context.snippet.is_empty());
+    }
+
+    #[test]
+    fn test_hover_function_signature() {
         let source = r#"
-system Counter @ 0.1.0 {
-    requires base >= 0.0.1
-    all counters is tracked
+fun add(a: i64, b: i64) -> i64 {
+    return a + b
+}
+        "#;
+        let nodes = parse_nodes(source).unwrap();
+        let line = node_line(&nodes[0]);
+        let info = hover_at(source, &nodes, line + 1, 5).unwrap();
+        assert_eq!(info.signature, "fun add(a: i64, b: i64) -> i64");
+        assert!(info.doc.is_none());
+    }
+
+    #[test]
+    fn test_hover_resolves_identifier() {
+        let source = r#"gen Point {
+    has x: i64
+    has y: i64
 }
 
 docs {
-    A counter system.
+    A point.
+}
+
+gen Segment {
+    has start: Point
+}
+
+docs {
+    A segment.
+}
+"#;
+        let nodes = parse_nodes(source).unwrap();
+        let at = |line: usize, column: usize| hover_at(source, &nodes, line, column).unwrap();
+
+        let field = at(3, 9);
+        assert_eq!(field.signature, "has y: i64");
+        assert!(field.doc.is_none());
+        // A type reference resolves to its declaration
+        let point = at(11, 17);
+        assert_eq!(point.signature, "gen Point");
+        assert_eq!(point.doc.as_deref(), Some("A point."));
+        // Keywords fall back to the enclosing declaration
+        assert_eq!(at(11, 5).signature, "gen Segment");
+    }
+
+    #[test]
+    fn test_project_system_versions() {
+        let system_at = |version: &str| {
+            format!(
+                "system Counter @ {} {{\n    requires base >= 0.0.1\n}}\n\ndocs {{\n}}\n",
+                version
+            )
+        };
+        let files = vec![
+            SourceFile {
+                path: "a.dol".to_string(),
+                source: system_at("0.1.0"),
+            },
+            SourceFile {
+                path: "b.dol".to_string(),
+                source: system_at("0.2.0"),
+            },
+        ];
+        let versions = collect_system_versions(&files);
+        let counter = &versions["Counter"];
+        assert_eq!(counter.len(), 2);
+        assert_eq!(counter[0].version, "0.1.0");
+        assert_eq!(counter[0].path, "a.dol");
+        assert_eq!(counter[1].version, "0.2.0");
+        assert_eq!(counter[1].path, "b.dol");
+    }
+
+    #[test]
+    fn test_lint_max_params() {
+        let source = r#"
+fun wide(a: i64, b: i64, c: i64, d: i64, e: i64, f: i64) -> i64 {
+    return a
+}
+
+fun narrow(a: i64, b: i64) -> i64 {
+    return a
 }
         "#;
-        assert!(validate_dol(source));
+        let options = CompileOptions {
+            max_params: Some(4),
+            ..CompileOptions::default()
+        };
+        let result = compile_with_options(source, &options);
+        assert!(result.success);
+        assert_eq!(result.warnings.len(), 1);
+        assert!(result.warnings[0].contains("'wide'"));
+        assert!(result.warnings[0].contains("declares 6 parameters"));
+
+        let result = compile_with_options(source, &CompileOptions::default());
+        assert!(result.warnings.is_empty());
     }
 
     #[test]
-    fn test_invalid_syntax() {
+    fn test_lint_max_function_lines() {
+        let body = "    total = total + 1\n".repeat(58);
+        let source = format!(
+            "fun long() {{\n{}}}\n\nfun short() {{\n    return 1\n}}\n",
+            body
+        );
+        let options = CompileOptions {
+            max_function_lines: Some(40),
+            ..CompileOptions::default()
+        };
+        let warnings = compile_with_options(&source, &options).warnings;
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("'long' at line 1 spans 60 lines"));
+    }
+
+    #[test]
+    fn test_deny_warnings() {
+        let source = "fun add(a: i64, b: i64) -> i64 {\n    return a + b\n}\n";
+        let options = CompileOptions {
+            max_params: Some(1),
+            deny_warnings: true,
+            ..CompileOptions::default()
+        };
+        let result = compile_with_options(source, &options);
+        assert!(!result.success);
+        assert_eq!(result.warnings.len(), 1);
+        assert_eq!(result.errors.len(), 1);
+        let error = &result.errors[0];
+        assert_eq!(error.error_type, "DenyWarnings");
+        assert_eq!(
+            error.message,
+            "compilation failed due to 1 warning (deny_warnings)"
+        );
+        assert_eq!((error.line, error.column, error.end_column), (1, 1, 2));
+    }
+
+    #[test]
+    fn test_errors_table_sorted() {
+        let error = |line: usize, column: usize, message: &str| CompileError {
+            message: message.to_string(),
+            line,
+            column,
+            end_line: line,
+            end_column: column + 1,
+            error_type: "ParseError".to_string(),
+        };
+        let table = render_errors_table(&[error(12, 1, "second"), error(3, 7, "first")]);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("LINE"));
+        assert!(lines[1].starts_with("3 ") && lines[1].ends_with("first"));
+        assert!(lines[2].starts_with("12") && lines[2].ends_with("second"));
+        assert_eq!(render_errors_table(&[]), "No errors.");
+    }
+
+    #[test]
+    fn test_parse_semver() {
+        assert_eq!(parse_semver("1.0.0"), Some((1, 0, 0)));
+        assert_eq!(parse_semver("0.12.3"), Some((0, 12, 3)));
+        assert_eq!(parse_semver("1.0.0-rc.1"), Some((1, 0, 0)));
+        assert_eq!(parse_semver("1.0.0+build.5"), Some((1, 0, 0)));
+        assert_eq!(parse_semver("1.2"), None);
+        assert_eq!(parse_semver("1..0"), None);
+        assert_eq!(parse_semver("01.0.0"), None);
+        assert_eq!(parse_semver("1.0.0-"), None);
+        assert_eq!(parse_semver("1.0.0-rc.01"), None);
+    }
+
+    #[test]
+    fn test_check_versions() {
+        let source = r#"system Good @ 1.0.0 {
+    requires base >= 0.0.1
+}
+
+docs {
+    A valid version.
+}
+
+system Bad @ 1.2 {
+    requires base >= 0.0.1
+}
+
+docs {
+    A version missing its patch number.
+}
+"#;
+        let checks = collect_version_checks(source);
+        assert_eq!(checks.len(), 2);
+        assert!(checks[0].valid);
+        assert_eq!(checks[1].system, "Bad");
+        assert_eq!(checks[1].version, "1.2");
+        assert!(!checks[1].valid);
+        assert_eq!(checks[1].line, 9);
+        assert!(collect_version_checks("system Broken @ 1.0.0 {").is_empty());
+    }
+
+    #[test]
+    fn test_find_references() {
         let source = r#"
-gen Unclosed {
-    has value
+gen Point {
+    has x: i64
+}
+
+gen Segment {
+    has start: Point
+    has end: Point // a Point in a comment is ignored
+}
         "#;
-        assert!(!validate_dol(source));
+        let refs = collect_references(source, "Point");
+        let contexts: Vec<&str> = refs.iter().map(|r| r.context.as_str()).collect();
+        assert_eq!(contexts, vec!["declaration", "type", "type"]);
+        assert_eq!((refs[1].line, refs[1].column), (7, 16));
     }
 
     #[test]
-    fn test_version() {
-        let version = get_version();
-        assert!(!version.is_empty());
+    fn test_single_use_types() {
+        let source = r#"gen Point {
+    has x: i64
+}
+
+docs {
+    A point.
+}
+
+gen Color {
+    has rgb: i64
+}
+
+docs {
+    A color.
+}
+
+gen Shape {
+    has origin: Point
+    has fill: Color
+    has stroke: Color
+}
+
+docs {
+    A shape.
+}
+"#;
+        assert_eq!(collect_single_use_types(source), vec!["Point"]);
+    }
+
+    #[test]
+    fn test_line_states_block_comment() {
+        let source = "gen A {\n/* first\n   middle\n   last */\n    a has b\n}";
+        assert_eq!(
+            compute_line_states(source),
+            vec![
+                LineState::Normal,
+                LineState::Normal,
+                LineState::InBlockComment,
+                LineState::InBlockComment,
+                LineState::Normal,
+                LineState::Normal,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_missing_docs() {
+        let source = r#"
+pub gen Bare {
+    has value: i32
+}
+
+docs {
+}
+
+pub gen Documented {
+    has value: i32
+}
+
+docs {
+    A documented gene.
+}
+
+gen Internal {
+    has value: i32
+}
+
+docs {
+}
+"#;
+        let options = CompileOptions {
+            require_docs: true,
+            ..CompileOptions::default()
+        };
+        let warnings = compile_with_options(source, &options).warnings;
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("MissingDocs: "));
+        assert!(warnings[0].contains("'Bare'"));
+        assert!(compile(source).warnings.is_empty());
+    }
+
+    #[test]
+    fn test_batch_diagnostics() {
+        let documents = vec![
+            TextDocument {
+                uri: "file:///clean.dol".to_string(),
+                text: "fun one() -> i64 {\n    return 1\n}\n".to_string(),
+            },
+            TextDocument {
+                uri: "file:///broken.dol".to_string(),
+                text: "gen Unclosed {\n    has value\n".to_string(),
+            },
+        ];
+        let diagnostics = batch_diagnostics(&documents);
+        assert_eq!(diagnostics.len(), 2);
+        assert!(diagnostics["file:///clean.dol"].is_empty());
+        let broken = &diagnostics["file:///broken.dol"];
+        assert_eq!(broken.len(), 1);
+        assert_eq!(broken[0].severity, 1);
+        assert_eq!(broken[0].source, "dol");
+    }
+
+    #[test]
+    fn test_lsp_warning_range() {
+        let source = "fun f() -> i64 {\n    return 1\n    x = 2\n}\n";
+        let diagnostics = lsp_diagnostics(source);
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(diagnostics[0].severity, 2);
+        assert_eq!(diagnostics[0].code, "UnreachableCode");
+        let range = diagnostics[0].range;
+        assert_eq!((range.start.line, range.start.character), (2, 0));
+        assert_eq!((range.end.line, range.end.character), (2, 9));
+    }
+
+    #[test]
+    fn test_builtin_shadowing() {
+        let gene = |name: &str| {
+            format!(
+                "gen {} {{\n    has value: i32\n}}\n\ndocs {{\n    A wrapper.\n}}\n",
+                name
+            )
+        };
+        let warnings = compile(&gene("String")).warnings;
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].starts_with("ShadowsBuiltin: "));
+        assert!(warnings[0].contains("gene 'String'"));
+        assert!(compile(&gene("Counter")).warnings.is_empty());
+    }
+
+    #[test]
+    fn test_builtin_widths() {
+        let source = r#"gen Packet {
+    has x: Int7
+    has y: Int32
+    has z: List<UInt0>
+    has w: Float64
+}
+
+docs {
+    A packet.
+}
+"#;
+        let result = compile(source);
+        assert!(result.success);
+        assert_eq!(
+            result.warnings,
+            vec![
+                "UnknownBuiltinWidth: unknown builtin type 'Int7' at line 2; \
+                 Int takes 8, 16, 32, 64 or 128 bits",
+                "UnknownBuiltinWidth: unknown builtin type 'UInt0' at line 4; \
+                 UInt takes 8, 16, 32, 64 or 128 bits",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_tree() {
+        let source = r#"
+gen Counter {
+    has value: i64
+    counter is tracked
+}
+
+docs {
+    A counter.
+}
+
+fun add(a: i64, b: i64) -> i64 {
+    return a + b
+}
+"#;
+        let tree = tree_view(source);
+        let lines: Vec<&str> = tree.lines().collect();
+        assert_eq!(
+            lines,
+            vec![
+                "├─ gen Counter",
+                "│  ├─ has value: i64",
+                "│  └─ counter is tracked",
+                "└─ fun add(a: i64, b: i64) -> i64",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lint_prefer_line_comments() {
+        let source = "/* one line */\n/*\n * spans lines\n */\n// already a line comment\n";
+        let warnings = lint_line_comments(source);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].kind, "PreferLineComment");
+        assert_eq!(warnings[0].line, 1);
+    }
+
+    #[test]
+    fn test_lint_read_only_methods() {
+        let source = r#"system Clock @ 1.0.0 {
+    has ticks: i64
+
+    fun peek() -> i64 {
+        return this.ticks
+    }
+
+    pub fun get() -> i64 {
+        return ticks
+    }
+
+    fun tick() -> i64 {
+        this.ticks = this.ticks + 1
+        return this.ticks
+    }
+
+    sex fun log() -> i64 {
+        return ticks
+    }
+}
+
+docs {
+    A clock.
+}
+"#;
+        let read_only = |exempt_pub_getters| {
+            let options = CompileOptions {
+                read_only_methods: true,
+                exempt_pub_getters,
+                ..CompileOptions::default()
+            };
+            compile_with_options(source, &options)
+                .warnings
+                .into_iter()
+                .filter(|w| w.starts_with("ReadOnlySpiritMethod:"))
+                .collect::<Vec<_>>()
+        };
+
+        let warnings = read_only(true);
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("method 'peek' at line 4"));
+        assert_eq!(read_only(false).len(), 2);
+        assert!(compile(source).warnings.is_empty());
+    }
+
+    #[test]
+    fn test_lint_duplicate_bodies() {
+        let source = r#"fun to_fahrenheit(x: i64) -> i64 {
+    return x * 9 / 5 + 32
+}
+
+fun scale(x: i64) -> i64 {
+    // Same tokens, different layout
+    return x*9/5 + 32
+}
+
+fun to_kelvin(x: i64) -> i64 {
+    return x + 273
+}
+"#;
+        let groups = collect_duplicate_bodies(source);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].functions, vec!["to_fahrenheit", "scale"]);
+        assert_eq!(groups[0].lines, vec![1, 5]);
+
+        let options = CompileOptions {
+            duplicate_bodies: true,
+            ..CompileOptions::default()
+        };
+        let result = compile_with_options(source, &options);
+        assert!(result.success);
+        assert_eq!(
+            result.warnings,
+            vec![
+                "DuplicateBody: function 'scale' at line 5 has the same body as \
+                 'to_fahrenheit' at line 1; extract a shared function"
+            ]
+        );
+        assert!(compile(source).warnings.is_empty());
+    }
+
+    #[test]
+    fn test_lint_magic_numbers() {
+        let source = r#"fun to_seconds(days: i64) -> i64 {
+    return days * 86400
+}
+
+fun next(x: i64) -> i64 {
+    return x + 1
+}
+"#;
+        let magic = |allowlist: Vec<String>| {
+            let options = CompileOptions {
+                warn_magic_numbers: true,
+                magic_number_allowlist: allowlist,
+                ..CompileOptions::default()
+            };
+            compile_with_options(source, &options).warnings
+        };
+
+        assert_eq!(
+            magic(vec![]),
+            vec![
+                "MagicNumber: magic number 86400 in function 'to_seconds' at line 2; \
+                 give it a named const"
+            ]
+        );
+        assert!(magic(vec!["86_400".to_string()]).is_empty());
+        assert!(compile(source).warnings.is_empty());
+
+        let source =
+            "fun area(r: f64) -> f64 {\n    const PI: f64 = 3.14159\n    return PI * r * r\n}\n";
+        assert!(lint_magic_numbers(source, &[]).is_empty());
     }
 }
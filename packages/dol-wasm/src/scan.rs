@@ -0,0 +1,326 @@
+//! Lightweight source scanner for editor-facing utilities
+//!
+//! metadol owns real tokenization; this module only splits source into
+//! words, literals, comments and punctuation with positions, so tooling
+//! such as lints and highlight helpers can reason about text that the
+//! converted AST does not carry (comments, brackets, raw identifiers).
+
+/// Broad lexical class of a scanned item
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum LexKind {
+    /// Identifier or keyword
+    Word,
+    /// Numeric literal, including any alphanumeric suffix
+    Number,
+    /// Quoted string or character literal, quotes included
+    Str,
+    /// `//` comment up to (not including) the newline
+    LineComment,
+    /// `/* ... */` comment, delimiters included
+    BlockComment,
+    /// Prose inside a `docs { ... }` / `exegesis { ... }` block, braces excluded
+    Doc,
+    /// Any other single character
+    Punct,
+}
+
+/// A scanned item with its position in the source
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Lexeme<'a> {
+    pub kind: LexKind,
+    pub text: &'a str,
+    /// 1-based line of the first character
+    pub line: usize,
+    /// 1-based column (in characters) of the first character
+    pub column: usize,
+    /// Byte offset of the first character
+    pub offset: usize,
+}
+
+impl Lexeme<'_> {
+    /// 1-based line of the last character
+    pub fn end_line(&self) -> usize {
+        self.line + self.text.matches('\n').count()
+    }
+
+    /// 1-based column (in characters) just past the last character
+    pub fn end_column(&self) -> usize {
+        match self.text.rfind('\n') {
+            Some(newline) => self.text[newline + 1..].chars().count() + 1,
+            None => self.column + self.text.chars().count(),
+        }
+    }
+
+    /// Whether this is punctuation equal to `c`
+    pub fn is_punct(&self, c: char) -> bool {
+        self.kind == LexKind::Punct && self.text.starts_with(c)
+    }
+
+    /// Whether this is a comment of either style
+    pub fn is_comment(&self) -> bool {
+        matches!(self.kind, LexKind::LineComment | LexKind::BlockComment)
+    }
+}
+
+/// Position-tracking cursor over the source
+struct Cursor<'a> {
+    source: &'a str,
+    offset: usize,
+    line: usize,
+    column: usize,
+}
+
+impl<'a> Cursor<'a> {
+    fn rest(&self) -> &'a str {
+        &self.source[self.offset..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.offset += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn bump_while(&mut self, pred: impl Fn(char) -> bool) {
+        while self.peek().is_some_and(&pred) {
+            self.bump();
+        }
+    }
+
+    fn advance_to(&mut self, offset: usize) {
+        while self.offset < offset && self.bump().is_some() {}
+    }
+}
+
+/// Length of a character literal at the start of `rest`, if there is one
+///
+/// Only single-line literals count, so apostrophes in prose don't open a
+/// literal that swallows the rest of the file.
+fn char_literal_len(rest: &str) -> Option<usize> {
+    let body = rest.strip_prefix('\'')?;
+    let line = body.split('\n').next().unwrap_or_default();
+    if let Some(escaped) = line.strip_prefix('\\') {
+        // Skip the escaped char itself, so `'\''` closes on the final quote
+        let c = escaped.chars().next()?;
+        let tail = &escaped[c.len_utf8()..];
+        tail.find('\'').map(|i| c.len_utf8() + i + 3)
+    } else {
+        let c = line.chars().next().filter(|&c| c != '\'')?;
+        line[c.len_utf8()..]
+            .starts_with('\'')
+            .then(|| c.len_utf8() + 2)
+    }
+}
+
+/// Byte length of a docs body at the start of `rest`, up to its closing brace
+fn doc_body_len(rest: &str) -> usize {
+    let mut depth = 0usize;
+    for (i, c) in rest.char_indices() {
+        match c {
+            '{' => depth += 1,
+            '}' if depth == 0 => return i,
+            '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    rest.len()
+}
+
+/// Split `source` into lexemes, skipping whitespace
+///
+/// Unterminated strings and block comments run to the end of the source.
+/// The body of a `docs`/`exegesis` block is kept as a single `Doc` lexeme
+/// (without surrounding whitespace) since it is prose, not code.
+pub(crate) fn scan(source: &str) -> Vec<Lexeme<'_>> {
+    let mut cursor = Cursor {
+        source,
+        offset: 0,
+        line: 1,
+        column: 1,
+    };
+    let mut lexemes: Vec<Lexeme> = Vec::new();
+
+    while let Some(c) = cursor.peek() {
+        if c.is_whitespace() {
+            cursor.bump();
+            continue;
+        }
+
+        let (start, line, column) = (cursor.offset, cursor.line, cursor.column);
+        let rest = cursor.rest();
+        let kind = if rest.starts_with("//") {
+            cursor.bump_while(|c| c != '\n');
+            LexKind::LineComment
+        } else if let Some(comment) = rest.strip_prefix("/*") {
+            let len = comment.find("*/").map_or(rest.len(), |i| i + 4);
+            cursor.advance_to(start + len);
+            LexKind::BlockComment
+        } else if c == '"' {
+            cursor.bump();
+            while let Some(next) = cursor.bump() {
+                if next == '\\' {
+                    cursor.bump();
+                } else if next == '"' {
+                    break;
+                }
+            }
+            LexKind::Str
+        } else if let Some(len) = char_literal_len(rest) {
+            cursor.advance_to(start + len);
+            LexKind::Str
+        } else if c.is_ascii_digit() {
+            while let Some(next) = cursor.peek() {
+                // A `.` only continues the number when a digit follows, so
+                // ranges like `1..2` keep their dots as punctuation
+                let decimal =
+                    next == '.' && cursor.rest()[1..].starts_with(|d: char| d.is_ascii_digit());
+                if !(next.is_ascii_alphanumeric() || next == '_' || decimal) {
+                    break;
+                }
+                cursor.bump();
+            }
+            LexKind::Number
+        } else if c.is_alphabetic() || c == '_' {
+            cursor.bump_while(|c| c.is_alphanumeric() || c == '_');
+            LexKind::Word
+        } else {
+            cursor.bump();
+            LexKind::Punct
+        };
+
+        let opens_docs = c == '{'
+            && kind == LexKind::Punct
+            && lexemes
+                .last()
+                .is_some_and(|l| l.kind == LexKind::Word && matches!(l.text, "docs" | "exegesis"));
+
+        lexemes.push(Lexeme {
+            kind,
+            text: &source[start..cursor.offset],
+            line,
+            column,
+            offset: start,
+        });
+
+        if opens_docs {
+            cursor.bump_while(char::is_whitespace);
+            let rest = cursor.rest();
+            let body = rest[..doc_body_len(rest)].trim_end();
+            if !body.is_empty() {
+                let (start, line, column) = (cursor.offset, cursor.line, cursor.column);
+                cursor.advance_to(start + body.len());
+                lexemes.push(Lexeme {
+                    kind: LexKind::Doc,
+                    text: body,
+                    line,
+                    column,
+                    offset: start,
+                });
+            }
+        }
+    }
+
+    lexemes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<(LexKind, &str)> {
+        scan(source).iter().map(|l| (l.kind, l.text)).collect()
+    }
+
+    #[test]
+    fn test_scan_words_and_punct() {
+        assert_eq!(
+            kinds("gen Counter { has value: i32 }"),
+            vec![
+                (LexKind::Word, "gen"),
+                (LexKind::Word, "Counter"),
+                (LexKind::Punct, "{"),
+                (LexKind::Word, "has"),
+                (LexKind::Word, "value"),
+                (LexKind::Punct, ":"),
+                (LexKind::Word, "i32"),
+                (LexKind::Punct, "}"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_comments_and_strings() {
+        let source = "a // note\n/* x\ny */ \"s \\\" }\" 'c' 1.5..2";
+        assert_eq!(
+            kinds(source),
+            vec![
+                (LexKind::Word, "a"),
+                (LexKind::LineComment, "// note"),
+                (LexKind::BlockComment, "/* x\ny */"),
+                (LexKind::Str, "\"s \\\" }\""),
+                (LexKind::Str, "'c'"),
+                (LexKind::Number, "1.5"),
+                (LexKind::Punct, "."),
+                (LexKind::Punct, "."),
+                (LexKind::Number, "2"),
+            ]
+        );
+        let lexemes = scan(source);
+        assert_eq!((lexemes[2].line, lexemes[2].column), (2, 1));
+        assert_eq!(lexemes[2].end_line(), 3);
+        assert_eq!(lexemes[2].end_column(), 5);
+        assert_eq!(lexemes[1].end_column(), 10);
+        assert_eq!((lexemes[3].line, lexemes[3].column), (3, 6));
+        assert_eq!(lexemes[3].offset, 20);
+    }
+
+    #[test]
+    fn test_scan_docs_body() {
+        let source = "docs {\n    It's a counter {nested}.\n}\nfun f";
+        assert_eq!(
+            kinds(source),
+            vec![
+                (LexKind::Word, "docs"),
+                (LexKind::Punct, "{"),
+                (LexKind::Doc, "It's a counter {nested}."),
+                (LexKind::Punct, "}"),
+                (LexKind::Word, "fun"),
+                (LexKind::Word, "f"),
+            ]
+        );
+        assert_eq!(kinds("docs {}").len(), 3);
+    }
+
+    #[test]
+    fn test_scan_escaped_char_literals() {
+        assert_eq!(
+            kinds(r"'\'' '\n' '\u{1F600}' x"),
+            vec![
+                (LexKind::Str, r"'\''"),
+                (LexKind::Str, r"'\n'"),
+                (LexKind::Str, r"'\u{1F600}'"),
+                (LexKind::Word, "x"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_scan_unterminated() {
+        let lexemes = scan("x /* open\nstill");
+        assert_eq!(lexemes.len(), 2);
+        assert_eq!(lexemes[1].text, "/* open\nstill");
+        let lexemes = scan("\"never closed");
+        assert_eq!(lexemes[0].kind, LexKind::Str);
+        assert_eq!(lexemes[0].text, "\"never closed");
+    }
+}
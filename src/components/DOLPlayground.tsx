@@ -440,6 +440,7 @@ interface CompilerOutput {
         constraint_count: number;
         system_count: number;
         function_count: number;
+        field_count: number;
         source_lines: number;
     };
 }
@@ -108,12 +108,12 @@ async function executeWasm(bytecode: Uint8Array, ast: object[]): Promise<Executi
           }
 
           // Convert args to BigInt for 64-bit types
-          // Parameter type format: { name: string, param_type: string } where param_type is like "Named(\"i64\")"
+          // Parameter type format: { name: string, param_type: string } where param_type is DOL source like "i64"
           const convertedArgs: (number | bigint)[] = sampleValues.map((val, idx) => {
             const param = fn.params?.[idx] as { name?: string; param_type?: string } | undefined;
             const paramType = param?.param_type || '';
             console.log(`[Worker] Param ${idx}: type="${paramType}"`);
-            // Check if type contains i64 or i128 (needs BigInt) - handles both "i64" and "Named(\"i64\")"
+            // Check if type contains i64 or i128 (needs BigInt)
             if (paramType.includes('64') || paramType.includes('128')) {
               return BigInt(val);
             }